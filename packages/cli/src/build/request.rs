@@ -3062,6 +3062,9 @@ impl BuildRequest {
                 .emit()
                 .context("Failed to emit wasm split modules")?;
 
+            // Let the glue detect split modules that came from a different build
+            writeln!(glue, "buildId = \"{}\";", modules.build_id)?;
+
             // Write the chunks that contain shared imports
             // These will be in the format of chunk_0_modulename.wasm - this is hardcoded in wasm-split
             tracing::debug!("Writing split chunks to disk");
@@ -3069,9 +3072,10 @@ impl BuildRequest {
                 let path = bindgen_outdir.join(format!("chunk_{}_{}.wasm", idx, chunk.module_name));
                 wasm_opt::write_wasm(&chunk.bytes, &path, &wasm_opt_options).await?;
                 writeln!(
-                    glue, "export const __wasm_split_load_chunk_{idx} = makeLoad(\"/assets/{url}\", [], fusedImports);",
+                    glue, "export const __wasm_split_load_chunk_{idx} = makeLoad(\"/assets/{url}\", [], fusedImports);\nregisterChunk(\"chunk_{idx}_{name}\", __wasm_split_load_chunk_{idx});",
                     url = assets
                         .register_asset(&path, AssetOptions::Unknown)?.bundled_path(),
+                    name = chunk.module_name,
                 )?;
             }
 
@@ -3093,7 +3097,7 @@ impl BuildRequest {
 
                 writeln!(
                     glue,
-                    "export const __wasm_split_load_{module}_{hash_id}_{comp_name} = makeLoad(\"/assets/{url}\", [{deps}], fusedImports);",
+                    "export const __wasm_split_load_{module}_{hash_id}_{comp_name} = makeLoad(\"/assets/{url}\", [{deps}], fusedImports);\nregisterModule(\"{comp_name}\", __wasm_split_load_{module}_{hash_id}_{comp_name});\nregisterChunk(\"module_{idx}_{comp_name}\", __wasm_split_load_{module}_{hash_id}_{comp_name});",
                     module = module.module_name,
 
 
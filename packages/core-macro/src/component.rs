@@ -196,8 +196,24 @@ impl ComponentBody {
                         static __MODULE: wasm_split::LazyLoader<#props_ty, #out_ty> =
                             wasm_split::lazy_loader!(extern "lazy" fn #lazy_name(props: #props_ty,) -> #out_ty);
 
-                        use_resource(|| async move { __MODULE.load().await }).suspend()?;
-                        __MODULE.call(props).unwrap()
+                        // With a delay set by `wasm_split::set_suspense_fallback_delay`, the component renders
+                        // nothing instead of suspending until the delay passed, so fast loads never show the
+                        // boundary's fallback. The delay is read on the first render only, so the timer's hook
+                        // is either there on every render or on none
+                        let __fallback_delay = dioxus::prelude::use_hook(wasm_split::suspense_fallback_delay);
+                        let __load = use_resource(|| async move { __MODULE.load_suspended().await });
+                        let __delay = __fallback_delay.map(|delay| {
+                            use_resource(move || wasm_split::suspense_fallback_delay_elapsed(Some(delay)))
+                        });
+                        if __delay.is_some_and(|delay| delay.read().is_none()) && __load.read().is_none() {
+                            return dioxus::prelude::VNode::empty();
+                        }
+                        __load.suspend()?;
+                        // A module that failed to load is thrown into the nearest error boundary
+                        dioxus::prelude::Context::context(
+                            __MODULE.call(props),
+                            concat!("Failed to load the lazy component `", stringify!(#fn_ident), "`"),
+                        )?
                     }
                 } else {
                     {
@@ -1,53 +1,155 @@
 // when running the harness we need to make sure to uncommon this out...
 
 export function makeLoad(url, deps, fusedImports, initIt) {
-  let alreadyLoaded = false;
-  return async (callbackIndex, callbackData) => {
-    await Promise.all(deps.map((dep) => dep()));
-    if (alreadyLoaded) return;
-    try {
-      const response = await fetch(url);
-      const initSync = initIt || window.__wasm_split_main_initSync;
-      const mainExports = initSync(undefined, undefined);
-
-      let imports = {
-        env: {
-          memory: mainExports.memory,
-        },
-        __wasm_split: {
-          __indirect_function_table: mainExports.__indirect_function_table,
-          __stack_pointer: mainExports.__stack_pointer,
-          __tls_base: mainExports.__tls_base,
-          memory: mainExports.memory,
-        },
-      };
-
-      for (let mainExport in mainExports) {
-        imports["__wasm_split"][mainExport] = mainExports[mainExport];
-      }
+  // The load of this module, shared by every caller so the module is only fetched once. It's
+  // cleared if the load fails so that it can be retried.
+  let loading;
+  // Resolves to whether the module loaded successfully
+  const loadModule = async (callbackIndex, callbackData) => {
+    // A response handed over with `LazySplitLoader::instantiate_from_response` replaces the fetch.
+    // It's taken before loading the deps so that they don't pick it up as well.
+    const options = window.__wasm_split_options || {};
+    const response = options.response;
+    delete options.response;
+    // The signal passed to `LazyLoader::load_with_signal` only applies to this load as well
+    const signal = options.signal;
+    delete options.signal;
+    // The callback passed to `LazyLoader::load_with_progress`, called with the bytes received so far
+    const progress = options.progress;
+    delete options.progress;
+    // The signal `LazyLoader::reload_with` aborts the fetch with to start it again
+    const restartSignal = options.restartSignal;
+    delete options.restartSignal;
+    // The exports required by `wasm_split::set_export_schema`
+    const exportSchema = options.exportSchema;
+    delete options.exportSchema;
+    // The policies set with `LazyLoader::configure`
+    const { timeout, priority, mirrors = [] } = options;
+    delete options.timeout;
+    delete options.priority;
+    delete options.mirrors;
 
-      for (let name in fusedImports) {
-        imports["__wasm_split"][name] = fusedImports[name];
-      }
+    // Starts a new attempt at the shared load with the options of this call
+    const attempt = () => {
+      const promise = (async () => {
+        await Promise.all(deps.map((dep) => dep()));
 
-      let new_exports = await WebAssembly.instantiateStreaming(
-        response,
-        imports
-      );
+        // Modules are fetched with the transport set by `wasm_split::set_transport`, if any
+        const transport = response ? undefined : options.transport;
+        const chunkUrl = hashedUrl(url);
+        const integrity = manifestIntegrity(url);
+        const fetchStart = now();
+        // This is either the response or the bytes of the module when using a custom transport
+        const source = response
+          ? response
+          : transport
+          ? await transport(resolveUrl(chunkUrl))
+          : await fetchModule(chunkUrl, mirrors, {
+              signal: withTimeout([signal, restartSignal], timeout),
+              priority,
+              integrity,
+            });
+        const fetchEnd = now();
+        if (response || transport) await checkIntegrity(source, integrity, url);
+        // The bytes of the module as they came over the wire, if the server told us
+        const size = source instanceof Response
+          ? Number(source.headers.get("Content-Length")) || undefined
+          : source.byteLength;
+        const { mainExports, imports } = moduleImports(initIt, fusedImports);
 
-      alreadyLoaded = true;
+        // Compile and instantiate separately so we can time each phase
+        const compileStart = now();
+        const limited = meterBody(withWasmMime(source), url, progress);
+        const module = await (transport
+          ? WebAssembly.compile(limited.source)
+          : WebAssembly.compileStreaming(limited.source)
+        ).catch(async (e) => {
+          if (limited.error || transport || !isCspError(e)) throw limited.error ?? e;
 
-      for (let name in new_exports.instance.exports) {
-        fusedImports[name] = new_exports.instance.exports[name];
-      }
+          // Some Content Security Policies only block compiling straight from a response, so
+          // fetch the module again and compile its bytes instead. The streamed response is
+          // already used up.
+          console.warn(
+            "Compiling wasm-split module from its response was blocked by the Content Security Policy, retrying with its bytes",
+            url
+          );
+          const retry = meterBody(
+            withWasmMime(
+              await fetchModule(chunkUrl, mirrors, {
+                signal: withTimeout([signal, restartSignal], timeout),
+                priority,
+                integrity,
+              })
+            ),
+            url
+          );
+          const bytes = await retry.source.arrayBuffer().catch((e) => {
+            throw retry.error ?? e;
+          });
+          return WebAssembly.compile(bytes);
+        });
+        checkBuildId(module, url);
+        checkExportSchema(module, url, exportSchema);
+        const instantiateStart = now();
+        const memoryBefore = mainExports.memory.buffer.byteLength;
+        const instance = await WebAssembly.instantiate(module, imports);
+        const instantiateEnd = now();
+        // The modules share the main module's memory, so this is only how much it grew during instantiation
+        const memory = mainExports.memory.buffer.byteLength - memoryBefore;
+
+        for (let name in instance.exports) {
+          fusedImports[name] = instance.exports[name];
+        }
+        // Read by `wasm_split::deferred_bytes` to tell which modules are still deferred
+        loadModule.loaded = true;
 
-      if (callbackIndex !== undefined) {
-        mainExports.__indirect_function_table.get(callbackIndex)(
-          callbackData,
-          true
-        );
+        return {
+          fetch: fetchEnd - fetchStart,
+          compile: instantiateStart - compileStart,
+          instantiate: instantiateEnd - instantiateStart,
+          memory,
+          size,
+          // The resource timing entry is only added once compiling has read the body
+          cacheHit: response || transport ? undefined : wasCacheHit(source),
+          exports: WebAssembly.Module.exports(module)
+            .filter((entry) => entry.kind === "function")
+            .map((entry) => entry.name),
+          // Kept so `LazyLoader::custom_section` can read the sections of the compiled module
+          module,
+          chunk: loadModule.chunk,
+        };
+      })();
+      // Kept so the callers that joined this attempt can tell when it was aborted to restart it
+      promise.restartSignal = restartSignal;
+      return promise;
+    };
+
+    let load = (loading ??= attempt());
+    let report;
+    try {
+      for (;;) {
+        try {
+          report = await load;
+          break;
+        } catch (e) {
+          // Callers that joined an attempt aborted by `LazyLoader::reload_with` join the attempt it
+          // restarted with instead, or start one if it hasn't restarted yet
+          if (load.restartSignal?.aborted && load.restartSignal !== restartSignal) {
+            if (loading === load) loading = undefined;
+            load = loading ??= attempt();
+            continue;
+          }
+          throw e;
+        }
       }
     } catch (e) {
+      if (loading === load) loading = undefined;
+      // A fetch aborted by `LazyLoader::reload_with` didn't fail, the loader starts it again as
+      // soon as it's called back, so there is nothing to log or report
+      if (restartSignal?.aborted) {
+        callLoader(initIt, callbackIndex, callbackData, false, undefined);
+        return false;
+      }
       console.error(
         "Failed to load wasm-split module",
         e,
@@ -55,9 +157,395 @@ export function makeLoad(url, deps, fusedImports, initIt) {
         deps,
         fusedImports
       );
-      return;
+      callLoader(initIt, callbackIndex, callbackData, false, {
+        error: errorKind(e),
+        missing: e.missing,
+      });
+      return false;
+    }
+
+    callLoader(initIt, callbackIndex, callbackData, true, report);
+    return true;
+  };
+
+  // Whether this module has been instantiated
+  loadModule.loaded = false;
+
+  // Instantiate a newer build of this module from another url for `LazyLoader::hot_swap`. Its
+  // element segments overwrite the table slots of the old instance, so calls across the split
+  // boundary go to the new functions from then on. The old instance is never freed.
+  loadModule.hotSwap = async (newUrl) => {
+    // The newer build is fetched like any other load, with the policies the loader handed over
+    const options = window.__wasm_split_options || {};
+    const { timeout, priority, mirrors = [], transport } = options;
+    delete options.timeout;
+    delete options.priority;
+    delete options.mirrors;
+
+    try {
+      const { imports } = moduleImports(initIt, fusedImports);
+      const chunkUrl = hashedUrl(newUrl);
+      const source = transport
+        ? await transport(resolveUrl(chunkUrl))
+        : await fetchModule(chunkUrl, mirrors, {
+            signal: withTimeout([], timeout),
+            priority,
+          });
+      const module = await (transport
+        ? WebAssembly.compile(source)
+        : WebAssembly.compileStreaming(withWasmMime(source)));
+      const instance = await WebAssembly.instantiate(module, imports);
+      for (let name in instance.exports) {
+        fusedImports[name] = instance.exports[name];
+      }
+      return true;
+    } catch (e) {
+      console.error("Failed to hot swap wasm-split module", e, url, newUrl);
+      return false;
     }
   };
+
+  return loadModule;
+}
+
+// The kind of error a load failed with, as reported to the runtime
+function errorKind(e) {
+  return e.name === "WasmSplitVersionMismatch"
+    ? "version-mismatch"
+    : e.name === "AbortError"
+    ? "aborted"
+    : e.name === "TimeoutError"
+    ? "timeout"
+    : e.name === "WasmSplitTooLarge"
+    ? "too-large"
+    : e.name === "WasmSplitSchemaMismatch"
+    ? "schema-mismatch"
+    : e.name === "WasmSplitUntrusted"
+    ? "untrusted"
+    : "failed";
+}
+
+// Fetch a static asset declared with `wasm_split::lazy_blob!` like a module, with the transport,
+// content hash, signed manifest and correlation id of the build. Resolves to the bytes of the
+// asset, or to the kind of error it failed with.
+window.__wasm_split_fetch_blob = async (url) => {
+  // The blob gets the default timeout the runtime hands over like the policies of a loader
+  const options = window.__wasm_split_options || {};
+  const { timeout, priority, mirrors = [], transport } = options;
+  delete options.timeout;
+  delete options.priority;
+  delete options.mirrors;
+
+  try {
+    const chunkUrl = hashedUrl(url);
+    const integrity = manifestIntegrity(url);
+    const source = transport
+      ? await transport(resolveUrl(chunkUrl))
+      : await fetchModule(chunkUrl, mirrors, {
+          signal: withTimeout([], timeout),
+          priority,
+          integrity,
+        });
+    if (transport) await checkIntegrity(source, integrity, url);
+    if (source instanceof Response && !source.ok) {
+      throw new Error(`wasm-split blob ${url} failed to load with status ${source.status}`);
+    }
+    const bytes = source instanceof Response ? await source.arrayBuffer() : source;
+    return { bytes: new Uint8Array(bytes) };
+  } catch (e) {
+    console.error("Failed to load wasm-split blob", e, url);
+    return { error: errorKind(e) };
+  }
+};
+
+// The imports a split module is instantiated with: the exports of the main module, including its
+// memory and function table, the exports of the modules loaded so far, and the shared imports
+function moduleImports(initIt, fusedImports) {
+  const initSync = initIt || window.__wasm_split_main_initSync;
+  const mainExports = initSync(undefined, undefined);
+
+  let imports = {
+    env: {
+      memory: mainExports.memory,
+    },
+    __wasm_split: {
+      __indirect_function_table: mainExports.__indirect_function_table,
+      __stack_pointer: mainExports.__stack_pointer,
+      __tls_base: mainExports.__tls_base,
+      memory: mainExports.memory,
+    },
+  };
+
+  for (let mainExport in mainExports) {
+    imports["__wasm_split"][mainExport] = mainExports[mainExport];
+  }
+
+  for (let name in fusedImports) {
+    imports["__wasm_split"][name] = fusedImports[name];
+  }
+
+  // The imports set with `wasm_split::set_shared_imports`, which never replace the ones above
+  const shared = (window.__wasm_split_options || {}).sharedImports || {};
+  for (let namespace in shared) {
+    imports[namespace] = { ...shared[namespace], ...imports[namespace] };
+  }
+
+  return { mainExports, imports };
+}
+
+// Call back into the loader that requested this module, if any, handing it the report of the load
+function callLoader(initIt, callbackIndex, callbackData, success, report) {
+  if (callbackIndex === undefined) return;
+  const initSync = initIt || window.__wasm_split_main_initSync;
+  const mainExports = initSync(undefined, undefined);
+
+  // The loader reads the report synchronously when the callback fires
+  window.__wasm_split_report = report;
+  mainExports.__indirect_function_table.get(callbackIndex)(callbackData, success);
+}
+
+// The current time in milliseconds, from the clock set with `wasm_split::testing::set_clock` if any
+function now() {
+  const clock = (window.__wasm_split_options || {}).clock;
+  return clock ? clock() : performance.now();
+}
+
+// Resolve the url of a module against a base url, defaulting to the one set with
+// `wasm_split::set_chunk_base_url`, and rewrite it with `wasm_split::set_url_rewriter`
+function resolveUrl(url, base = (window.__wasm_split_options || {}).chunkBaseUrl) {
+  const resolved = base ? base.replace(/\/$/, "") + url : url;
+  const rewrite = (window.__wasm_split_options || {}).urlRewriter;
+  return rewrite ? rewrite(resolved) : resolved;
+}
+
+// The url of a module with the content hash set with `wasm_split::set_chunk_hashes`, looked up by
+// its file name without the `.wasm` extension. The hashed url keeps the extension of the file, so
+// blobs keep theirs. Modules without a hash keep their url.
+function hashedUrl(url) {
+  const hashes = (window.__wasm_split_options || {}).chunkHashes || {};
+  const file = url.split("/").pop();
+  const hash = hashes[file.replace(/\.wasm$/, "")];
+  const extension = file.match(/\.[^.]*$/)?.[0] ?? "";
+  return hash === undefined ? url : `/${hash}${extension}`;
+}
+
+// The integrity of a module in the manifest verified by `wasm_split::set_signed_manifest`, refusing
+// modules that aren't in it. Without a manifest every module is trusted.
+function manifestIntegrity(url) {
+  const manifest = (window.__wasm_split_options || {}).chunkIntegrity;
+  if (manifest === undefined) return undefined;
+
+  const integrity = manifest[url.split("/").pop().replace(/\.wasm$/, "")];
+  if (integrity === undefined) {
+    throw untrusted(`wasm-split module ${url} isn't in the signed manifest`);
+  }
+  return integrity;
+}
+
+// Check a module that wasn't fetched by `fetchModule` against its integrity, since the browser only
+// checks the integrity of fetches
+async function checkIntegrity(source, integrity, url) {
+  if (integrity === undefined) return;
+
+  const separator = integrity.indexOf("-");
+  const algorithm = integrity.slice(0, separator).replace("sha", "SHA-");
+  const bytes = source instanceof Response ? await source.clone().arrayBuffer() : source;
+  const digest = new Uint8Array(await crypto.subtle.digest(algorithm, bytes));
+  if (btoa(String.fromCharCode(...digest)) !== integrity.slice(separator + 1)) {
+    throw untrusted(`wasm-split module ${url} doesn't match its hash in the signed manifest`);
+  }
+}
+
+function untrusted(message) {
+  const error = new Error(message);
+  error.name = "WasmSplitUntrusted";
+  return error;
+}
+
+// Fetch a module, falling back to each of the mirrors in order if the request fails. The mirrors
+// share the correlation id of the fetch set up with `wasm_split::set_correlation_id_provider`.
+async function fetchModule(url, mirrors, init) {
+  const { correlationId, correlationHeader } = window.__wasm_split_options || {};
+  if (correlationId) init = { ...init, headers: { [correlationHeader]: correlationId() } };
+  const bases = [undefined, ...mirrors];
+  for (let i = 0; i < bases.length; i++) {
+    const last = i === bases.length - 1;
+    try {
+      const response = await fetch(resolveUrl(url, bases[i]), init);
+      if (response.ok || last) return response;
+    } catch (e) {
+      // Aborting or timing out applies to every mirror
+      if (last || init.signal?.aborted) throw e;
+    }
+  }
+}
+
+// Guess whether a response was served from the HTTP cache by its resource timing: nothing went over
+// the wire even though a body arrived. Cross-origin responses without a `Timing-Allow-Origin` header
+// report zero for both sizes, so they can't be told apart and are `undefined`.
+function wasCacheHit(response) {
+  const [entry] = performance.getEntriesByName(response.url, "resource").slice(-1);
+  if (entry === undefined || entry.decodedBodySize === 0) return undefined;
+  return entry.transferSize === 0;
+}
+
+// Reject modules larger than the limit set with `wasm_split::set_max_chunk_bytes`, and report the
+// bytes received to the progress callback of the load. The Content-Length header is checked first,
+// and then the body as it streams in since the header can be missing or wrong. The error is kept on
+// the result since the compiler may wrap stream errors.
+function meterBody(source, url, progress) {
+  const max = (window.__wasm_split_options || {}).maxChunkBytes;
+  const limited = { source, error: undefined };
+  if (max === undefined && progress === undefined) return limited;
+
+  const tooLarge = () => {
+    const error = new Error(`wasm-split module ${url} is larger than the limit of ${max} bytes`);
+    error.name = "WasmSplitTooLarge";
+    return error;
+  };
+
+  if (!(source instanceof Response)) {
+    if (source.byteLength > max) throw tooLarge();
+    progress?.(source.byteLength, source.byteLength);
+    return limited;
+  }
+  const total = Number(source.headers.get("Content-Length")) || undefined;
+  if (total > max) throw tooLarge();
+  if (!source.body) return limited;
+
+  let received = 0;
+  // Progress is reported at most once per `wasm_split::set_progress_throttle` interval, except for
+  // the first and the last report
+  const throttle = (window.__wasm_split_options || {}).progressThrottle ?? 0;
+  let reported = received;
+  let reportedAt = now();
+  const report = (force) => {
+    const time = now();
+    if (!force && time - reportedAt < throttle) return;
+    reported = received;
+    reportedAt = time;
+    progress?.(received, total);
+  };
+  progress?.(received, total);
+  const body = source.body.pipeThrough(
+    new TransformStream({
+      transform(chunk, controller) {
+        received += chunk.byteLength;
+        if (received > max) {
+          limited.error = tooLarge();
+          controller.error(limited.error);
+        } else {
+          report(received === total);
+          controller.enqueue(chunk);
+        }
+      },
+      // The last chunk may have been throttled if the total isn't known, so report it at the end
+      flush() {
+        if (reported !== received) report(true);
+      },
+    })
+  );
+  limited.source = new Response(body, {
+    status: source.status,
+    statusText: source.statusText,
+    headers: source.headers,
+  });
+  return limited;
+}
+
+// Re-wrap a response served with the type set with `wasm_split::set_expected_mime` in one with the
+// `application/wasm` type that streaming compilation requires. The body is handed over as-is, so it
+// streams straight into the compiler without being fetched again.
+function withWasmMime(source) {
+  const mime = (window.__wasm_split_options || {}).expectedMime;
+  if (mime === undefined || !(source instanceof Response)) return source;
+
+  const type = source.headers.get("Content-Type")?.split(";")[0].trim().toLowerCase();
+  if (type !== mime) return source;
+
+  const headers = new Headers(source.headers);
+  headers.set("Content-Type", "application/wasm");
+  return new Response(source.body, {
+    status: source.status,
+    statusText: source.statusText,
+    headers,
+  });
+}
+
+// Check whether compiling a module failed because the Content Security Policy doesn't allow it.
+// Browsers don't give these a distinct error type, so this goes by the message.
+function isCspError(e) {
+  return /Content Security Policy|CSP|unsafe-eval/.test(e?.message ?? "");
+}
+
+// Combine the signals of the load with its timeout, if any
+function withTimeout(signals, timeout) {
+  const all = signals.filter(Boolean);
+  if (timeout !== undefined) all.push(timeoutSignal(timeout));
+  return all.length > 1 ? AbortSignal.any(all) : all[0];
+}
+
+// A signal that times out after the timeout, on the timers of the clock set with
+// `wasm_split::testing::set_clock` if there is one
+function timeoutSignal(timeout) {
+  const setTimer = (window.__wasm_split_options || {}).setTimer;
+  if (!setTimer) return AbortSignal.timeout(timeout);
+
+  const controller = new AbortController();
+  setTimer(timeout, () =>
+    controller.abort(new DOMException("The load timed out", "TimeoutError"))
+  );
+  return controller.signal;
+}
+
+// The id of the build that produced the main module, written by the bundler after this glue. Split
+// modules carry the id of their build in the `__wasm_split_build_id` custom section.
+let buildId;
+
+// Make sure a module was built from the same build as the main module. A stale main bundle running
+// against freshly deployed modules would otherwise fail to instantiate with a cryptic error.
+function checkBuildId(module, url) {
+  const [section] = WebAssembly.Module.customSections(module, "__wasm_split_build_id");
+  if (buildId === undefined || section === undefined) return;
+
+  const moduleBuildId = new TextDecoder().decode(section);
+  if (moduleBuildId !== buildId) {
+    const error = new Error(
+      `wasm-split module ${url} is from build ${moduleBuildId} but the main module is from build ${buildId}`
+    );
+    error.name = "WasmSplitVersionMismatch";
+    throw error;
+  }
+}
+
+// Make sure a module exports every function in its schema, before it's instantiated
+function checkExportSchema(module, url, schema) {
+  if (schema === undefined) return;
+
+  const exports = new Set(WebAssembly.Module.exports(module).map((entry) => entry.name));
+  const missing = schema.filter((name) => !exports.has(name));
+  if (missing.length > 0) {
+    const error = new Error(`wasm-split module ${url} is missing the exports ${missing.join(", ")}`);
+    error.name = "WasmSplitSchemaMismatch";
+    error.missing = missing;
+    throw error;
+  }
+}
+
+// The load functions of every split module by the name of its split function, used by
+// `wasm_split::load_critical` to load modules whose loaders haven't been used yet
+window.__wasm_split_modules = {};
+function registerModule(name, load) {
+  (window.__wasm_split_modules[name] ??= []).push(load);
+}
+
+// The load functions of every module and shared chunk by their file name without the extension,
+// used by `wasm_split::preload_chunks`
+window.__wasm_split_chunks = {};
+function registerChunk(name, load) {
+  window.__wasm_split_chunks[name] = load;
+  // Reported with every load so `wasm_split::loaded_chunk_count` can tell the chunks apart
+  load.chunk = name;
 }
 
 let fusedImports = {};
@@ -3,7 +3,7 @@ use itertools::Itertools;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
-    hash::Hash,
+    hash::{DefaultHasher, Hash, Hasher},
     ops::Range,
     sync::{Arc, RwLock},
 };
@@ -47,6 +47,10 @@ pub struct Splitter<'a> {
     data_symbols: BTreeMap<usize, DataSymbol>,
     main_graph: HashSet<Node>,
     call_graph: HashMap<Node, HashSet<Node>>,
+
+    // An id unique to this build that gets embedded in every split module so the glue can detect
+    // modules that were built from a different build than the main module
+    build_id: String,
     parent_graph: HashMap<Node, HashSet<Node>>,
 }
 
@@ -60,6 +64,10 @@ pub struct OutputModules {
 
     /// The chunks that might be imported by the main modules
     pub chunks: Vec<SplitModule>,
+
+    /// The id of this build. This is embedded in the `__wasm_split_build_id` custom section of every
+    /// split module and needs to be written into the glue as `buildId = "<id>";`
+    pub build_id: String,
 }
 
 /// A wasm module that was split from the main module.
@@ -90,6 +98,10 @@ impl<'a> Splitter<'a> {
         // since that's not reliable after bindgening
         let raw_data = parse_bytes_to_data_segment(bindgened)?;
 
+        let mut hasher = DefaultHasher::new();
+        original.hash(&mut hasher);
+        let build_id = format!("{:016x}", hasher.finish());
+
         let mut module = Self {
             source_module: module,
             original,
@@ -103,6 +115,7 @@ impl<'a> Splitter<'a> {
             call_graph: Default::default(),
             parent_graph: Default::default(),
             shared_symbols: Default::default(),
+            build_id,
         };
 
         module.build_call_graph()?;
@@ -132,6 +145,8 @@ impl<'a> Splitter<'a> {
             .map(|idx| self.emit_split_module(idx))
             .collect::<Result<Vec<SplitModule>>>()?;
 
+        let build_id = self.build_id.clone();
+
         // Emit the main module, consuming self since we're going to
         let main = self.emit_main_module()?;
 
@@ -139,6 +154,7 @@ impl<'a> Splitter<'a> {
             modules,
             chunks,
             main,
+            build_id,
         })
     }
 
@@ -280,6 +296,9 @@ impl<'a> Splitter<'a> {
         // Remove the reloc and linking custom sections
         self.remove_custom_sections(&mut out);
 
+        // Tag the module with the build it belongs to
+        self.add_build_id_section(&mut out);
+
         // Run the gc to remove unused functions - also validates the module to ensure we can emit it properly
         // todo(jon): prefer to delete the items as we go so we don't need to run a gc pass. it/it's quite slow
         walrus::passes::gc::run(&mut out);
@@ -352,6 +371,9 @@ impl<'a> Splitter<'a> {
         // Remove the reloc and linking custom sections
         self.remove_custom_sections(&mut out);
 
+        // Tag the chunk with the build it belongs to
+        self.add_build_id_section(&mut out);
+
         // Run the gc to remove unused functions - also validates the module to ensure we can emit it properly
         walrus::passes::gc::run(&mut out);
 
@@ -876,6 +898,14 @@ impl<'a> Splitter<'a> {
         }
     }
 
+    /// Embed the build id in a custom section so the glue can detect modules from a different build
+    fn add_build_id_section(&self, out: &mut Module) {
+        out.customs.add(walrus::RawCustomSection {
+            name: "__wasm_split_build_id".to_string(),
+            data: self.build_id.as_bytes().to_vec(),
+        });
+    }
+
     /// Accumulate any shared funcs between multiple chunks into a single residual chunk.
     /// This prevents duplicates from being downloaded.
     /// Eventually we need to group the chunks into smarter "communities" - ie the Louvain algorithm
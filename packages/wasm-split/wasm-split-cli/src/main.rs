@@ -61,7 +61,7 @@ fn split(args: SplitArgs) {
     // Write the js module
     std::fs::write(
         args.out_dir.join("__wasm_split.js"),
-        emit_js(&chunks.chunks, &chunks.modules),
+        emit_js(&chunks.chunks, &chunks.modules, &chunks.build_id),
     )
     .expect("failed to write js module");
 
@@ -105,11 +105,13 @@ fn split(args: SplitArgs) {
     }
 }
 
-fn emit_js(chunks: &[SplitModule], modules: &[SplitModule]) -> String {
+fn emit_js(chunks: &[SplitModule], modules: &[SplitModule], build_id: &str) -> String {
     use std::fmt::Write;
     let mut glue = format!(
         r#"import {{ initSync }} from "./main.js";
-{}"#,
+{}
+buildId = "{build_id}";
+"#,
         include_str!("./__wasm_split.js")
     );
 
@@ -117,7 +119,7 @@ fn emit_js(chunks: &[SplitModule], modules: &[SplitModule]) -> String {
         tracing::debug!("emitting chunk: {:?}", chunk.module_name);
         writeln!(
                 glue,
-                "export const __wasm_split_load_chunk_{idx} = makeLoad(\"/harness/split/chunk_{idx}_{module}.wasm\", [], fusedImports, initSync);",
+                "export const __wasm_split_load_chunk_{idx} = makeLoad(\"/harness/split/chunk_{idx}_{module}.wasm\", [], fusedImports, initSync);\nregisterChunk(\"chunk_{idx}_{module}\", __wasm_split_load_chunk_{idx});",
                 module = chunk.module_name
             ).expect("failed to write to string");
     }
@@ -134,7 +136,7 @@ fn emit_js(chunks: &[SplitModule], modules: &[SplitModule]) -> String {
 
         writeln!(
                 glue,
-                "export const __wasm_split_load_{module}_{hash_id}_{cname} = makeLoad(\"/harness/split/module_{idx}_{cname}.wasm\", [{deps}], fusedImports, initSync);",
+                "export const __wasm_split_load_{module}_{hash_id}_{cname} = makeLoad(\"/harness/split/module_{idx}_{cname}.wasm\", [{deps}], fusedImports, initSync);\nregisterModule(\"{cname}\", __wasm_split_load_{module}_{hash_id}_{cname});\nregisterChunk(\"module_{idx}_{cname}\", __wasm_split_load_{module}_{hash_id}_{cname});",
                 module = module.module_name,
                 idx = idx,
                 cname = module.component_name.as_ref().unwrap(),
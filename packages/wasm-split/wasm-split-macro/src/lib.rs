@@ -2,24 +2,151 @@ use proc_macro::TokenStream;
 
 use digest::Digest;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, parse_quote, FnArg, Ident, ItemFn, ReturnType, Signature};
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    Abi, AngleBracketedGenericArguments, Data, DeriveInput, FnArg, Ident, ItemFn, LitStr,
+    ReturnType, Signature, Token, Type,
+};
 
+/// Split an async function into its own module that is downloaded the first time it's called.
+///
+/// ```rust, ignore
+/// #[wasm_split(editor)]
+/// async fn open_editor(doc: Doc) -> Editor {}
+/// ```
+///
+/// ## Splitting a whole module
+///
+/// A `mod` of cohesive functions can be split at once, with `module = "name"` naming the split
+/// module they share. Every public function in it is split as if it had the attribute itself, so
+/// they all end up in the same split module and each downloads it on its first call:
+///
+/// ```rust, ignore
+/// #[wasm_split(module = "editor")]
+/// mod editor {
+///     pub async fn open(doc: Doc) -> Editor {}
+///     pub async fn export(editor: &Editor) -> Vec<u8> {}
+///
+///     fn layout(doc: &Doc) -> Layout {}
+/// }
+/// ```
+///
+/// Only functions directly in the module with a `pub` visibility of any kind, like `pub(crate)`,
+/// are split, and each of them must be `async`. Private functions and every other item are left as
+/// they are, so helpers that only the split functions call end up in the split module with them,
+/// while anything the rest of the app uses stays in the main module. Nested modules and methods in
+/// `impl` blocks aren't walked. The module must be written inline, since the attribute can't see
+/// the contents of a `mod editor;` in another file.
 #[proc_macro_attribute]
 pub fn wasm_split(args: TokenStream, input: TokenStream) -> TokenStream {
-    let module_ident = parse_macro_input!(args as Ident);
-    let item_fn = parse_macro_input!(input as ItemFn);
+    let SplitArgs { module_ident } = parse_macro_input!(args as SplitArgs);
+    let item = parse_macro_input!(input as syn::Item);
+
+    match item {
+        syn::Item::Fn(item_fn) => {
+            if item_fn.sig.asyncness.is_none() {
+                panic!("wasm_split functions must be async. Use a LazyLoader with synchronous functions instead.");
+            }
+            split_fn(&module_ident, item_fn).into()
+        }
+        syn::Item::Mod(item_mod) => split_mod(&module_ident, item_mod)
+            .unwrap_or_else(syn::Error::into_compile_error)
+            .into(),
+        item => syn::Error::new_spanned(
+            item,
+            "wasm_split only applies to async functions and modules",
+        )
+        .into_compile_error()
+        .into(),
+    }
+}
+
+/// The arguments of `#[wasm_split]`, either the name of the split module or `module = "name"`
+struct SplitArgs {
+    module_ident: Ident,
+}
 
-    if item_fn.sig.asyncness.is_none() {
-        panic!("wasm_split functions must be async. Use a LazyLoader with synchronous functions instead.");
+impl Parse for SplitArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let option: Ident = input.parse()?;
+            if option != "module" {
+                return Err(syn::Error::new_spanned(option, "unknown wasm_split option"));
+            }
+            input.parse::<Token![=]>()?;
+            let module: LitStr = input.parse()?;
+            let module_ident = syn::parse_str(&module.value()).map_err(|_| {
+                syn::Error::new_spanned(&module, "the module name must be an identifier")
+            })?;
+            return Ok(Self { module_ident });
+        }
+
+        Ok(Self {
+            module_ident: input.parse()?,
+        })
     }
+}
 
+/// Split every public function of an inline module into the same split module
+fn split_mod(
+    module_ident: &Ident,
+    mut item_mod: syn::ItemMod,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let Some((_, items)) = item_mod.content.take() else {
+        return Err(syn::Error::new_spanned(
+            &item_mod,
+            "wasm_split can only split modules written inline",
+        ));
+    };
+
+    let mut split_items = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            syn::Item::Fn(item_fn) if !matches!(item_fn.vis, syn::Visibility::Inherited) => {
+                if item_fn.sig.asyncness.is_none() {
+                    return Err(syn::Error::new_spanned(
+                        &item_fn.sig,
+                        "public functions of a split module must be async. Make it private or use a LazyLoader with synchronous functions instead.",
+                    ));
+                }
+                split_items.push(split_fn(module_ident, item_fn));
+            }
+            item => split_items.push(quote! { #item }),
+        }
+    }
+
+    let syn::ItemMod {
+        attrs,
+        vis,
+        unsafety,
+        mod_token,
+        ident,
+        ..
+    } = item_mod;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #unsafety #mod_token #ident {
+            #(#split_items)*
+        }
+    })
+}
+
+/// Split an async function into the split module with the given name
+fn split_fn(module_ident: &Ident, item_fn: ItemFn) -> proc_macro2::TokenStream {
     let LoaderNames {
+        name,
         split_loader_ident,
         impl_import_ident,
         impl_export_ident,
         load_module_ident,
-        ..
-    } = LoaderNames::new(item_fn.sig.ident.clone(), module_ident.to_string());
+    } = LoaderNames::new(
+        item_fn.sig.ident.clone(),
+        module_ident.to_string(),
+        &item_fn.sig.ident.to_string(),
+    );
 
     let mut desugard_async_sig = item_fn.sig.clone();
     desugard_async_sig.asyncness = None;
@@ -54,23 +181,24 @@ pub fn wasm_split(args: TokenStream, input: TokenStream) -> TokenStream {
             syn::FnArg::Typed(pat_type) => {
                 let param_ident = format_ident!("__wasm_split_arg_{i}");
                 args.push(param_ident.clone());
-                pat_type.pat = Box::new(syn::Pat::Ident(syn::PatIdent {
+                *pat_type.pat = syn::Pat::Ident(syn::PatIdent {
                     attrs: vec![],
                     by_ref: None,
                     mutability: None,
                     ident: param_ident,
                     subpat: None,
-                }));
+                });
             }
         }
     }
 
+    let vis = &item_fn.vis;
     let attrs = &item_fn.attrs;
     let stmts = &item_fn.block.stmts;
 
     quote! {
         #[cfg(target_arch = "wasm32")]
-        #wrapper_sig {
+        #vis #wrapper_sig {
             #(#attrs)*
             #[allow(improper_ctypes_definitions)]
             #[no_mangle]
@@ -93,7 +221,7 @@ pub fn wasm_split(args: TokenStream, input: TokenStream) -> TokenStream {
 
             thread_local! {
                 static #split_loader_ident: wasm_split::LazySplitLoader = unsafe {
-                    wasm_split::LazySplitLoader::new(#load_module_ident)
+                    wasm_split::LazySplitLoader::new(stringify!(#name), #load_module_ident)
                 };
             }
 
@@ -108,7 +236,6 @@ pub fn wasm_split(args: TokenStream, input: TokenStream) -> TokenStream {
         #[cfg(not(target_arch = "wasm32"))]
         #default_item
     }
-    .into()
 }
 
 /// Create a lazy loader for a given function. Meant to be used in statics. Designed for libraries to
@@ -117,77 +244,391 @@ pub fn wasm_split(args: TokenStream, input: TokenStream) -> TokenStream {
 /// ```rust, ignore
 /// fn SomeFunction(args: Args) -> Ret {}
 ///
-/// static LOADER: wasm_split::LazyLoader<Args, Ret> = lazy_loader!(SomeFunction);
+/// static LOADER: wasm_split::LazyLoader<Args, Ret> = lazy_loader!(extern "auto" fn SomeFunction(args: Args) -> Ret);
 ///
 /// LOADER.load().await.call(args)
 /// ```
+///
+/// ## Generic functions
+///
+/// Generic functions can't be monomorphized across the split boundary, so each instantiation needs
+/// its own loader. The concrete types must be named explicitly with a turbofish:
+///
+/// ```rust, ignore
+/// fn Process<T: Encode>(x: T) -> u32 {}
+///
+/// static PROCESS_FOO: wasm_split::LazyLoader<Foo, u32> = lazy_loader!(extern "auto" fn Process::<Foo>(x: Foo) -> u32);
+/// static PROCESS_BAR: wasm_split::LazyLoader<Bar, u32> = lazy_loader!(extern "auto" fn Process::<Bar>(x: Bar) -> u32);
+/// ```
+///
+/// ## Streaming results
+///
+/// A split can return `impl Trait`, like a stream of results for a large dataset. Since the concrete
+/// type can't cross the split boundary, the result is boxed and the loader returns a
+/// `Pin<Box<dyn Trait>>` from `call`:
+///
+/// ```rust, ignore
+/// fn Rows(query: Query) -> impl Stream<Item = Row> {}
+///
+/// static ROWS: wasm_split::LazyLoader<Query, Pin<Box<dyn Stream<Item = Row>>>> = lazy_loader!(extern "auto" fn Rows(query: Query) -> impl Stream<Item = Row>);
+/// ```
+///
+/// ## Async splits
+///
+/// The future of an `async fn` can't be named, so it can't be the return type of a loader. Declare
+/// the split to return a boxed future instead, and the future the function returns is boxed at the
+/// split boundary. `call` then hands back the boxed future to await:
+///
+/// ```rust, ignore
+/// async fn Fetch(id: Id) -> Record {}
+///
+/// static FETCH: wasm_split::LazyLoader<Id, Pin<Box<dyn Future<Output = Record>>>> = lazy_loader!(extern "auto" fn Fetch(id: Id) -> Pin<Box<dyn Future<Output = Record>>>);
+///
+/// let record = FETCH.call(id)?.await;
+/// ```
+///
+/// Boxing costs an allocation per call and a dynamic call every time the future is polled, which
+/// is negligible next to the work an async split usually does but adds up for futures that are
+/// created in a hot loop. The future must be `'static`, so it can't borrow from the arguments. A
+/// function that already returns a boxed future is boxed a second time, so prefer returning
+/// `impl Future` from it.
+///
+/// ## Out-parameters
+///
+/// To hand back several values without allocating, a split can write into a `&mut` out-parameter
+/// after its argument. The out-parameter crosses the split as a raw pointer, so the macro returns
+/// a `wasm_split::OutLoader` instead, which is only called with `OutLoader::call_out`. That borrows
+/// the out-parameter mutably for the duration of the call:
+///
+/// ```rust, ignore
+/// fn Measure(text: Text, out: &mut Metrics) {}
+///
+/// static MEASURE: wasm_split::OutLoader<Text, Metrics> = lazy_loader!(extern "auto" fn Measure(text: Text, out: &mut Metrics));
+///
+/// let mut metrics = Metrics::default();
+/// MEASURE.call_out(text, &mut metrics)?;
+/// ```
+///
+/// ## Feature-gated splits
+///
+/// A split can be tied to a cargo feature of the crate invoking the macro. When the feature is
+/// disabled, the loader is already loaded like `LazyLoader::preloaded` and the function is linked
+/// into the main module like any other function instead of being split out.
+///
+/// ```rust, ignore
+/// static PREMIUM: wasm_split::LazyLoader<Args, Ret> = lazy_loader!(feature = "premium", extern "auto" fn Premium(args: Args) -> Ret);
+/// ```
 #[proc_macro]
 pub fn lazy_loader(input: TokenStream) -> TokenStream {
     // We can only accept idents/paths that will be the source function
-    let sig = parse_macro_input!(input as Signature);
-    let params = sig.inputs.clone();
-    let outputs = sig.output.clone();
-    let Some(FnArg::Typed(arg)) = params.first().cloned() else {
+    let LazyLoaderInput {
+        feature,
+        module,
+        name,
+        turbofish,
+        inputs,
+        output: outputs,
+    } = parse_macro_input!(input as LazyLoaderInput);
+    let Some(FnArg::Typed(arg)) = inputs.first().cloned() else {
         panic!(
             "Lazy Loader must define a single input argument to satisfy the LazyLoader signature"
         )
     };
-    let arg_ty = arg.ty.clone();
+    let mut arg_ty = arg.ty.clone();
+
+    // A trailing `&mut Out` is an out-parameter. References can't be stored in the static loader,
+    // so it crosses the split as a raw pointer next to the argument and is reborrowed on the other
+    // side for the duration of the call.
+    let mut call_args = quote! { arg };
+    let mut wrap_loader = quote! { loader };
+    match inputs.iter().nth(1) {
+        Some(FnArg::Typed(out)) => {
+            let Type::Reference(out_ref) = &*out.ty else {
+                return syn::Error::new_spanned(
+                    &out.ty,
+                    "the second argument of a lazy loader must be a `&mut` out-parameter",
+                )
+                .to_compile_error()
+                .into();
+            };
+            if out_ref.mutability.is_none() {
+                return syn::Error::new_spanned(
+                    &out.ty,
+                    "out-parameters of lazy loaders must be `&mut` references",
+                )
+                .to_compile_error()
+                .into();
+            }
+            let out_ty = &out_ref.elem;
+            arg_ty = parse_quote! { (#arg_ty, *mut #out_ty) };
+            call_args = quote! { arg.0, unsafe { &mut *arg.1 } };
+            // The pointer only comes from the `&mut` handed to `call_out`
+            wrap_loader = quote! { unsafe { wasm_split::OutLoader::new(loader) } };
+        }
+        Some(FnArg::Receiver(receiver)) => {
+            return syn::Error::new_spanned(receiver, "lazy loaders can't take `self`")
+                .to_compile_error()
+                .into();
+        }
+        None => {}
+    }
+    if let Some(extra) = inputs.iter().nth(2) {
+        return syn::Error::new_spanned(
+            extra,
+            "lazy loaders take a single argument and an optional out-parameter",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let display_name = match &turbofish {
+        Some(turbofish) => format!("{name}{}", quote!(#turbofish).to_string().replace(' ', "")),
+        None => name.to_string(),
+    };
     let LoaderNames {
         name,
         split_loader_ident,
         impl_import_ident,
         impl_export_ident,
         load_module_ident,
-        ..
-    } = LoaderNames::new(
-        sig.ident.clone(),
-        sig.abi
-            .as_ref()
-            .and_then(|abi| abi.name.as_ref().map(|f| f.value()))
-            .expect("abi to be module name")
-            .to_string(),
-    );
+    } = LoaderNames::new(name, module, &display_name);
+
+    // `impl Trait` can't cross the split boundary, so the return value is boxed into a `dyn Trait`
+    let boxed_future = matches!(&outputs, ReturnType::Type(_, ty) if is_boxed_future(ty));
+    let (outputs, call) = match outputs {
+        ReturnType::Type(_, ty) if matches!(*ty, Type::ImplTrait(_)) => {
+            let Type::ImplTrait(impl_trait) = *ty else {
+                unreachable!()
+            };
+            let bounds = impl_trait.bounds;
+            (
+                parse_quote! { -> ::std::pin::Pin<::std::boxed::Box<dyn #bounds>> },
+                quote! { ::std::boxed::Box::pin(#name #turbofish (#call_args)) },
+            )
+        }
+        // A split declared to return a boxed future boxes whatever future the function returns, so
+        // `async fn`s can be split without naming their future
+        outputs if boxed_future => (
+            outputs,
+            quote! { ::std::boxed::Box::pin(#name #turbofish (#call_args)) },
+        ),
+        outputs => (outputs, quote! { #name #turbofish (#call_args) }),
+    };
+
+    // The real loader is only emitted on wasm and, if requested, when the feature is enabled
+    let split_cfg = match feature {
+        Some(feature) => quote! { all(target_arch = "wasm32", feature = #feature) },
+        None => quote! { target_arch = "wasm32" },
+    };
 
     quote! {
         {
-            #[cfg(target_arch = "wasm32")]
-            {
-                #[link(wasm_import_module = "./__wasm_split.js")]
-                extern "C" {
-                    // The function we'll use to initiate the download of the module
-                    #[no_mangle]
-                    fn #load_module_ident(
-                        callback: unsafe extern "C" fn(*const ::std::ffi::c_void, bool),
-                        data: *const ::std::ffi::c_void,
-                    );
+            let loader = {
+                #[cfg(#split_cfg)]
+                {
+                    #[link(wasm_import_module = "./__wasm_split.js")]
+                    extern "C" {
+                        // The function we'll use to initiate the download of the module
+                        #[no_mangle]
+                        fn #load_module_ident(
+                            callback: unsafe extern "C" fn(*const ::std::ffi::c_void, bool),
+                            data: *const ::std::ffi::c_void,
+                        );
 
-                    #[allow(improper_ctypes)]
+                        #[allow(improper_ctypes)]
+                        #[no_mangle]
+                        fn #impl_import_ident(arg: #arg_ty) #outputs;
+                    }
+
+
+                    #[allow(improper_ctypes_definitions)]
                     #[no_mangle]
-                    fn #impl_import_ident(arg: #arg_ty) #outputs;
-                }
+                    pub extern "C" fn #impl_export_ident(arg: #arg_ty) #outputs {
+                        #call
+                    }
 
+                    thread_local! {
+                        static #split_loader_ident: wasm_split::LazySplitLoader = unsafe {
+                            wasm_split::LazySplitLoader::new(#display_name, #load_module_ident)
+                        };
+                    };
 
-                #[allow(improper_ctypes_definitions)]
-                #[no_mangle]
-                pub extern "C" fn #impl_export_ident(arg: #arg_ty) #outputs {
-                    #name(arg)
+                    unsafe {
+                        wasm_split::LazyLoader::new(#impl_import_ident, &#split_loader_ident)
+                    }
                 }
 
-                thread_local! {
-                    static #split_loader_ident: wasm_split::LazySplitLoader = unsafe {
-                        wasm_split::LazySplitLoader::new(#load_module_ident)
+                #[cfg(not(#split_cfg))]
+                {
+                    fn #impl_export_ident(arg: #arg_ty) #outputs {
+                        #call
+                    }
+
+                    thread_local! {
+                        static #split_loader_ident: wasm_split::LazySplitLoader =
+                            wasm_split::LazySplitLoader::inlined(#display_name);
                     };
-                };
 
-                unsafe {
-                    wasm_split::LazyLoader::new(#impl_import_ident, &#split_loader_ident)
+                    unsafe {
+                        wasm_split::LazyLoader::inlined(#impl_export_ident, &#split_loader_ident)
+                    }
                 }
+            };
+            #wrap_loader
+        }
+    }
+    .into()
+}
+
+/// Check whether a type is written as `Pin<Box<dyn Future<..>>>`, with any paths to the types
+fn is_boxed_future(ty: &Type) -> bool {
+    // The single generic type argument of a path type whose last segment is `name`
+    fn generic_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+        let Type::Path(path) = ty else {
+            return None;
+        };
+        let segment = path.path.segments.last()?;
+        if segment.ident != name {
+            return None;
+        }
+        let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    let Some(Type::TraitObject(object)) =
+        generic_arg(ty, "Pin").and_then(|boxed| generic_arg(boxed, "Box"))
+    else {
+        return false;
+    };
+    object.bounds.iter().any(|bound| {
+        matches!(bound, syn::TypeParamBound::Trait(bound)
+            if bound.path.segments.last().is_some_and(|segment| segment.ident == "Future"))
+    })
+}
+
+/// The signature passed to `lazy_loader!`, ie `extern "module" fn Name::<Generics>(arg: Arg) -> Ret`
+///
+/// This is almost a regular function signature except that generic functions are named with a
+/// turbofish of their concrete types instead of declaring their generic parameters. The signature
+/// may be preceded by options like `feature = "name",`.
+struct LazyLoaderInput {
+    feature: Option<LitStr>,
+    module: String,
+    name: Ident,
+    turbofish: Option<AngleBracketedGenericArguments>,
+    inputs: Punctuated<FnArg, Token![,]>,
+    output: ReturnType,
+}
+
+impl Parse for LazyLoaderInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut feature = None;
+        while input.peek(Ident) && input.peek2(Token![=]) {
+            let option: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match option.to_string().as_str() {
+                "feature" => feature = Some(input.parse()?),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        option,
+                        "unknown lazy_loader option",
+                    ))
+                }
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        let abi: Abi = input.parse()?;
+        let module = abi
+            .name
+            .map(|name| name.value())
+            .ok_or_else(|| input.error("expected the abi to be the module name"))?;
+        input.parse::<Token![fn]>()?;
+        let name = input.parse()?;
+        let turbofish = if input.peek(Token![::]) {
+            Some(AngleBracketedGenericArguments::parse_turbofish(input)?)
+        } else {
+            None
+        };
+        let content;
+        parenthesized!(content in input);
+        let inputs = content.parse_terminated(FnArg::parse, Token![,])?;
+        let output = input.parse()?;
+
+        Ok(Self {
+            feature,
+            module,
+            name,
+            turbofish,
+            inputs,
+            output,
+        })
+    }
+}
+
+/// Derive group operations over a struct whose fields are all `&'static LazyLoader<_, _>`.
+///
+/// This generates `preload_all`, `load_all`, and `progress` methods that operate on every field of
+/// the struct in declaration order.
+///
+/// ```rust, ignore
+/// #[derive(LoaderRegistry)]
+/// struct EditorLoaders {
+///     toolbar: &'static LazyLoader<ToolbarProps, Element>,
+///     canvas: &'static LazyLoader<CanvasProps, Element>,
+/// }
+///
+/// let results = LOADERS.load_all().await;
+/// let (loaded, total) = LOADERS.progress();
+/// ```
+#[proc_macro_derive(LoaderRegistry)]
+pub fn loader_registry(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let Data::Struct(data) = &input.data else {
+        return syn::Error::new_spanned(&input, "LoaderRegistry can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+
+    let fields = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let idx = syn::Index::from(idx);
+                quote! { #idx }
+            }
+        })
+        .collect::<Vec<_>>();
+    let count = fields.len();
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Start downloading the modules of every loader without waiting for them to finish
+            pub fn preload_all(&self) {
+                #(self.#fields.preload();)*
+            }
+
+            /// Load every loader, returning whether each one loaded successfully in field order
+            pub async fn load_all(&self) -> Vec<bool> {
+                self.preload_all();
+                vec![#(self.#fields.load().await),*]
             }
 
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                wasm_split::LazyLoader::preloaded(#name)
+            /// Get the number of loaders that have loaded successfully and the total number of loaders
+            pub fn progress(&self) -> (usize, usize) {
+                let loaded: [bool; #count] = [#(self.#fields.is_loaded()),*];
+                (loaded.iter().filter(|loaded| **loaded).count(), #count)
             }
         }
     }
@@ -203,10 +644,11 @@ struct LoaderNames {
 }
 
 impl LoaderNames {
-    fn new(name: Ident, module: String) -> Self {
+    /// Create the names for a split function. The display name is hashed alongside the span so that
+    /// separate instantiations of a generic function get their own symbols.
+    fn new(name: Ident, module: String, display_name: &str) -> Self {
         let unique_identifier = base16::encode_lower(
-            &sha2::Sha256::digest(format!("{name} {span:?}", name = name, span = name.span()))
-                [..16],
+            &sha2::Sha256::digest(format!("{display_name} {span:?}", span = name.span()))[..16],
         );
 
         Self {
@@ -0,0 +1,64 @@
+//! Measuring what calling a split function through its [`LazyLoader`] costs compared to calling a
+//! function directly, for deciding whether a small split is worth keeping split out.
+
+use crate::{glue, warn_log, LazyLoader};
+use std::{hint::black_box, time::Duration};
+
+/// Call the split function of `loader` `iterations` times and return how much longer each call
+/// took on average than a direct call to a function that does nothing.
+///
+/// Point this at a split function that does nothing itself, so the difference is only the cost of
+/// going through [`LazyLoader::call`]: checking the state of the loader, and calling the function
+/// indirectly through the table of the main module. The same loop is timed once with the split and
+/// once with a no-op that's never inlined, with the same arguments built with `Default`, so the cost
+/// of the loop and of building the arguments cancels out. The arguments and results go through
+/// [`black_box`] so the compiler can't optimize either loop away.
+///
+/// Load the loader first, or this measures the call returning the error of a loader that isn't
+/// loaded, and logs a warning. The timer only has a resolution of a few microseconds in most
+/// browsers, so use enough iterations for the whole loop to take a few milliseconds. Measure in
+/// release builds, since debug builds add overhead of their own to both loops.
+///
+/// ```rust, ignore
+/// static NOOP: LazyLoader<(), ()> = lazy_loader!(extern "noop" fn noop());
+///
+/// NOOP.load().await;
+/// let overhead = wasm_split::benchmark::measure_call_overhead(&NOOP, 100_000);
+/// ```
+pub fn measure_call_overhead<Args: Default, Ret>(
+    loader: &'static LazyLoader<Args, Ret>,
+    iterations: u32,
+) -> Duration {
+    if !loader.is_loaded() {
+        warn_log(format_args!(
+            "measure_call_overhead: the loader isn't loaded, so this measures its error path"
+        ));
+    }
+    if iterations == 0 {
+        return Duration::ZERO;
+    }
+
+    let split = time(iterations, || {
+        _ = black_box(loader.call(black_box(Args::default())));
+    });
+    let direct = time(iterations, || {
+        _ = black_box(noop(black_box(Args::default())));
+    });
+
+    let overhead = (split - direct).max(0.0) / iterations as f64;
+    Duration::from_secs_f64(overhead / 1000.0)
+}
+
+#[inline(never)]
+fn noop<Args>(args: Args) -> Args {
+    args
+}
+
+/// How long calling `f` `iterations` times took, in milliseconds
+fn time(iterations: u32, mut f: impl FnMut()) -> f64 {
+    let start = glue::now();
+    for _ in 0..iterations {
+        f();
+    }
+    glue::now() - start
+}
@@ -0,0 +1,184 @@
+use crate::{Result, SplitLoaderError};
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    thread::LocalKey,
+};
+
+/// Declare a static [`LazyBlob`] that fetches a large static asset, like a lookup table, only once
+/// it is needed instead of bundling it into the main module.
+///
+/// The path is the url of the asset, resolved against the base url set with
+/// [`set_chunk_base_url`](crate::set_chunk_base_url) and looked up in the hashes and the signed
+/// manifest by its file name, like `table.bin`, the same way as the urls of split modules.
+///
+/// ```rust, ignore
+/// wasm_split::lazy_blob!(pub TABLE, "/assets/table.bin");
+///
+/// let table: &'static [u8] = TABLE.load().await?;
+/// ```
+#[macro_export]
+macro_rules! lazy_blob {
+    ($vis:vis $name:ident, $path:literal) => {
+        $vis static $name: $crate::LazyBlob = {
+            ::std::thread_local! {
+                static BLOB: $crate::LazyBlobState = $crate::LazyBlobState::new(::std::stringify!($name));
+            }
+
+            $crate::LazyBlob::new($path, &BLOB)
+        };
+    };
+}
+
+/// A static asset that is fetched the first time it is loaded. Declare one with [`lazy_blob!`].
+///
+/// Blobs are fetched by the glue like split modules, with the same transport, url rewriter, content
+/// hashes, signed manifest and correlation id. They also wait for
+/// [`resume_loads`](crate::resume_loads) and for the limit of
+/// [`set_max_concurrent_loads`](crate::set_max_concurrent_loads) like them, with the priority set
+/// for the name of the blob's static. Concurrent loads share a single download. The bytes are leaked once they arrive so that they can be handed out as
+/// `&'static [u8]`, which means a loaded blob stays in memory for the rest of the program.
+pub struct LazyBlob {
+    path: &'static str,
+    key: &'static LocalKey<LazyBlobState>,
+}
+
+impl LazyBlob {
+    #[doc(hidden)]
+    pub const fn new(path: &'static str, key: &'static LocalKey<LazyBlobState>) -> Self {
+        Self { path, key }
+    }
+
+    /// Load the blob, fetching it if this is the first load.
+    ///
+    /// A blob that failed to load is fetched again by the next load. Outside the browser there is
+    /// nothing to fetch from, so this always fails with [`SplitLoaderError::FailedToLoad`].
+    pub async fn load(&'static self) -> Result<&'static [u8]> {
+        self.start();
+        BlobFuture { key: self.key }.await
+    }
+
+    /// The url the blob is fetched from, before it is resolved against the chunk base url
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Get the bytes of the blob if it has already loaded, without starting a load
+    pub fn get(&'static self) -> Option<&'static [u8]> {
+        match self.key.with(|inner| inner.state.get()) {
+            BlobState::Loaded(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn start(&'static self) {
+        let key = self.key;
+        let start = key.with(|inner| match inner.state.get() {
+            BlobState::Deferred | BlobState::Failed => {
+                inner.state.set(BlobState::Pending);
+                true
+            }
+            BlobState::Pending | BlobState::Loaded(_) => false,
+        });
+        if !start {
+            return;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::{config, glue, pause};
+
+            let path = self.path;
+            let id = (key as *const LocalKey<LazyBlobState>).cast();
+            let fetch = move || {
+                pause::started(id);
+                config::spawn(Box::pin(async move {
+                    let result = glue::fetch_blob(path).await;
+                    let result = result.map(|bytes| &*Box::leak(bytes.into_boxed_slice()));
+                    pause::finished(id);
+                    key.with(|inner| inner.complete(result));
+                }));
+            };
+            config::ensure_initialized();
+            pause::start_or_queue(key.with(|inner| inner.name), fetch);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        key.with(|inner| inner.complete(Err(SplitLoaderError::FailedToLoad)));
+    }
+}
+
+#[doc(hidden)]
+pub struct LazyBlobState {
+    name: &'static str,
+    state: Cell<BlobState>,
+    wakers: RefCell<Vec<Waker>>,
+    error: RefCell<Option<SplitLoaderError>>,
+}
+
+impl LazyBlobState {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            state: Cell::new(BlobState::Deferred),
+            wakers: RefCell::new(Vec::new()),
+            error: RefCell::new(None),
+        }
+    }
+
+    fn complete(&self, result: Result<&'static [u8]>) {
+        match result {
+            Ok(bytes) => self.state.set(BlobState::Loaded(bytes)),
+            Err(err) => {
+                crate::hooks::error(&err, self.name);
+                self.error.replace(Some(err));
+                self.state.set(BlobState::Failed);
+            }
+        }
+
+        let scheduling = crate::config::callback_scheduling();
+        for waker in self.wakers.take() {
+            match scheduling {
+                crate::CallbackScheduling::Immediate => waker.wake(),
+                crate::CallbackScheduling::Raf => crate::glue::wake_on_next_frame(waker),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BlobState {
+    Deferred,
+    Pending,
+    Loaded(&'static [u8]),
+    Failed,
+}
+
+struct BlobFuture {
+    key: &'static LocalKey<LazyBlobState>,
+}
+
+impl Future for BlobFuture {
+    type Output = Result<&'static [u8]>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.key.with(|inner| match inner.state.get() {
+            BlobState::Loaded(bytes) => Poll::Ready(Ok(bytes)),
+            BlobState::Failed => Poll::Ready(Err(inner
+                .error
+                .borrow()
+                .clone()
+                .unwrap_or(SplitLoaderError::FailedToLoad))),
+            BlobState::Deferred | BlobState::Pending => {
+                let mut wakers = inner.wakers.borrow_mut();
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        })
+    }
+}
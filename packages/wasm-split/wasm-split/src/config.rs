@@ -0,0 +1,628 @@
+//! Global options for the split loading runtime.
+//!
+//! The options that change how modules are fetched, like the base url, the transport or the
+//! hashes, are set once at startup and then handed to the glue when the runtime is initialized,
+//! either explicitly with [`init`] or implicitly by the first load. Setting one of them after that
+//! is ignored with a warning. The options the runtime reads as it goes, like the logger, the
+//! debounces, timeouts, thresholds and limits, and strict mode, can be changed at any time instead
+//! and apply to whatever happens afterwards, like the loads that start or complete from then on.
+//! Each setter only documents the default it starts out with.
+
+use crate::{glue, warn_log, ChunkTransport};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    time::Duration,
+};
+
+thread_local! {
+    static CONFIG: RefCell<Config> = RefCell::new(Config::default());
+    static INITIALIZED: Cell<bool> = const { Cell::new(false) };
+    static CALLBACK_SCHEDULING: Cell<CallbackScheduling> = const { Cell::new(CallbackScheduling::Immediate) };
+    static PRELOAD_DEBOUNCE: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    static INSTANTIATE_WARN_THRESHOLD: Cell<Option<Duration>> = const { Cell::new(None) };
+    static STRICT_MODE: Cell<bool> = const { Cell::new(false) };
+    static DEFAULT_LOAD_TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+    static FAILURE_THRESHOLD: Cell<Option<u32>> = const { Cell::new(None) };
+    static SUSPENSE_FALLBACK_DELAY: Cell<Option<Duration>> = const { Cell::new(None) };
+    static PROGRESS_THROTTLE: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    static SPAWN_FN: RefCell<Option<SpawnFn>> = const { RefCell::new(None) };
+    static LOGGER: RefCell<Option<Logger>> = const { RefCell::new(None) };
+    static CHUNK_SIZES: RefCell<Option<HashMap<String, u64>>> = const { RefCell::new(None) };
+}
+
+/// A future the runtime drives in the background
+pub(crate) type SpawnedFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// The function set with [`set_spawn_fn`]
+type SpawnFn = Rc<dyn Fn(SpawnedFuture)>;
+
+/// The function set with [`set_logger`]
+type Logger = Rc<dyn Fn(LogLevel, &str)>;
+
+/// The function set with [`set_url_rewriter`]
+pub(crate) type UrlRewriter = Rc<dyn Fn(&str) -> String>;
+
+/// The function set with [`set_correlation_id_provider`]
+pub(crate) type CorrelationIdProvider = Rc<dyn Fn() -> String>;
+
+#[derive(Default)]
+struct Config {
+    chunk_base_url: Option<String>,
+    transport: Option<Rc<dyn ChunkTransport>>,
+    url_rewriter: Option<UrlRewriter>,
+    correlation_id_provider: Option<CorrelationIdProvider>,
+    correlation_header: Option<String>,
+    max_chunk_bytes: Option<u64>,
+    expected_mime: Option<String>,
+    chunk_hashes: Option<HashMap<String, String>>,
+    chunk_integrity: Option<HashMap<String, String>>,
+    #[cfg(target_arch = "wasm32")]
+    shared_imports: Option<wasm_bindgen::JsValue>,
+}
+
+/// Initialize the split loading runtime.
+///
+/// This hands all the options set with functions like [`set_chunk_base_url`] to the glue that
+/// fetches the split modules. Once initialized, these options are locked and any further calls to
+/// their setters are ignored with a warning, while the options the runtime reads as it goes can
+/// still be changed. Call this once before rendering anything.
+///
+/// If a loader is used before `init` is called, the runtime is initialized implicitly with whatever
+/// options were set up to that point, and the options are locked from then on.
+///
+/// The options are handed over synchronously, so there's nothing to wait for: the glue is ready as
+/// soon as this returns, and modules are only fetched once a loader starts loading.
+pub fn init() {
+    ensure_initialized();
+}
+
+/// Set the base URL that split modules are fetched from, like `https://cdn.example.com`.
+///
+/// By default modules are fetched from the same origin as the main bundle. The URLs of the modules
+/// are appended to the base URL as-is, after trimming a trailing `/` from the base URL since the
+/// URLs of the modules start with one, so `https://cdn.example.com/` works as well.
+pub fn set_chunk_base_url(url: impl Into<String>) {
+    let url = url.into();
+    configure("set_chunk_base_url", |config| {
+        config.chunk_base_url = Some(url)
+    });
+}
+
+/// Set the content hashes of the split modules, for deployments that serve every module at
+/// `/<hash>.wasm` so it can be cached forever.
+///
+/// The map goes from the logical name of a module to its hash. The logical name is the file name
+/// the bundler gave the module without its `.wasm` extension, like `module_0_Editor` or
+/// `chunk_0_app`. A module with a hash is fetched from `/<hash>.wasm` instead of its usual url,
+/// resolved against the base URL set with [`set_chunk_base_url`] and the mirrors of its loader like
+/// any other url. A module whose name has no hash in the map is still fetched from its usual url.
+pub fn set_chunk_hashes(hashes: HashMap<String, String>) {
+    configure("set_chunk_hashes", |config| {
+        config.chunk_hashes = Some(hashes)
+    });
+}
+
+/// Refuse to load any module that isn't in the map, and check the others against their integrity,
+/// for `set_signed_manifest`
+#[cfg(feature = "signed-manifest")]
+pub(crate) fn set_chunk_integrity(integrity: HashMap<String, String>) {
+    configure("set_signed_manifest", |config| {
+        config.chunk_integrity = Some(integrity)
+    });
+}
+
+/// Set a function that rewrites the url of every split module right before it is fetched, like to
+/// route requests to a regional CDN, pick a CDN for an A/B test, or append an auth token.
+///
+/// `rewriter` is called with the default url of the module, fully resolved against the base URL
+/// set with [`set_chunk_base_url`] and after its content hash from [`set_chunk_hashes`] was
+/// applied, and returns the url to fetch instead:
+///
+/// ```rust, ignore
+/// wasm_split::set_url_rewriter(|url| format!("{url}?token={}", session_token()));
+/// ```
+///
+/// It's called for every request: once per mirror of a loader as each one is tried, and again for
+/// every retry. The transport set with [`set_transport`] receives the rewritten url, so the
+/// rewriter always runs before the transport.
+pub fn set_url_rewriter(rewriter: impl Fn(&str) -> String + 'static) {
+    configure("set_url_rewriter", |config| {
+        config.url_rewriter = Some(Rc::new(rewriter))
+    });
+}
+
+/// Set a function that creates a correlation id for every fetch of a split module, so a request for
+/// a module can be traced from the browser through the CDN and server logs.
+///
+/// The id is sent in the `X-Correlation-Id` header unless another header was set with
+/// [`set_correlation_header`]. Every fetch gets a new id, while the mirrors tried for the same fetch
+/// share it:
+///
+/// ```rust, ignore
+/// wasm_split::set_correlation_id_provider(|| uuid::Uuid::new_v4().to_string());
+/// ```
+///
+/// A custom header makes cross-origin fetches send a CORS preflight request first, so a server on
+/// another origin has to allow the header. Modules downloaded by a transport set with
+/// [`set_transport`] don't get the header, since the transport sends its own requests.
+pub fn set_correlation_id_provider(provider: impl Fn() -> String + 'static) {
+    configure("set_correlation_id_provider", |config| {
+        config.correlation_id_provider = Some(Rc::new(provider))
+    });
+}
+
+/// Set the name of the header that carries the ids from [`set_correlation_id_provider`], like
+/// `traceparent` or `X-Request-Id`, instead of `X-Correlation-Id`.
+pub fn set_correlation_header(header: impl Into<String>) {
+    let header = header.into();
+    configure("set_correlation_header", |config| {
+        config.correlation_header = Some(header)
+    });
+}
+
+/// Set the transport used to download split modules instead of the glue's built-in HTTP fetching.
+///
+/// See [`ChunkTransport`] for details.
+pub fn set_transport(transport: Box<dyn ChunkTransport>) {
+    configure("set_transport", |config| {
+        config.transport = Some(transport.into())
+    });
+}
+
+/// Set the largest size in bytes a split module may have, as a safety valve against a
+/// misconfigured CDN serving the wrong file.
+///
+/// A module whose `Content-Length` is larger than this is rejected before it is downloaded, and a
+/// module whose body turns out to be larger while it streams in is aborted. Either way the load
+/// fails with [`SplitLoaderError::TooLarge`](crate::SplitLoaderError::TooLarge). By default the
+/// size of modules is unlimited.
+pub fn set_max_chunk_bytes(max: u64) {
+    configure("set_max_chunk_bytes", |config| {
+        config.max_chunk_bytes = Some(max)
+    });
+}
+
+/// Set the `Content-Type` the host serves split modules with, for static hosts that don't serve
+/// `.wasm` files as `application/wasm`, like ones that fall back to `application/octet-stream`.
+///
+/// Browsers only compile a module while it streams in if it's served as `application/wasm`. A module
+/// whose response has the type set here is handed to the compiler in a new response with the right
+/// type wrapped around the same body, so it still streams in over the original request instead of
+/// being fetched a second time. The type is compared without its parameters, like `charset`, and
+/// ignoring case. Modules served with any other wrong type still fail to load. By default only
+/// `application/wasm` is accepted.
+pub fn set_expected_mime(mime: &str) {
+    let mime = mime.trim().to_ascii_lowercase();
+    configure("set_expected_mime", |config| {
+        config.expected_mime = Some(mime)
+    });
+}
+
+/// Set extra imports that every split module is instantiated with, for modules that call host
+/// functions the main module doesn't export.
+///
+/// `imports` is an import object like the one passed to `WebAssembly.instantiate`, mapping the
+/// module names the splits import from to objects of their imports:
+///
+/// ```rust, ignore
+/// let imports = js_sys::eval("({ host: { now: () => performance.now() } })").unwrap();
+/// wasm_split::set_shared_imports(imports);
+/// ```
+///
+/// Namespaces are merged into the imports the glue builds. New namespaces are added as-is, and
+/// imports added to the glue's own `env` and `__wasm_split` namespaces never replace the main
+/// module's exports like its memory and function table. A split module that expects an import
+/// that isn't present fails to instantiate, so its load fails with
+/// [`SplitLoaderError::FailedToLoad`](crate::SplitLoaderError::FailedToLoad) and the missing import
+/// is logged to the console.
+#[cfg(target_arch = "wasm32")]
+pub fn set_shared_imports(imports: wasm_bindgen::JsValue) {
+    configure("set_shared_imports", |config| {
+        config.shared_imports = Some(imports)
+    });
+}
+
+/// When the tasks waiting on a loader are woken after its module finishes loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CallbackScheduling {
+    /// Wake waiting tasks as soon as the module is loaded. This has the lowest latency.
+    #[default]
+    Immediate,
+
+    /// Defer waking waiting tasks to the next `requestAnimationFrame` so the DOM updates caused by
+    /// loads batch together with the render loop.
+    Raf,
+}
+
+/// Set when tasks waiting on a loader are woken after its module finishes loading.
+///
+/// Defaults to [`CallbackScheduling::Immediate`].
+pub fn set_callback_scheduling(scheduling: CallbackScheduling) {
+    CALLBACK_SCHEDULING.set(scheduling);
+}
+
+pub(crate) fn callback_scheduling() -> CallbackScheduling {
+    CALLBACK_SCHEDULING.get()
+}
+
+/// Set the function that spawns the futures the runtime drives in the background, for apps that
+/// want them on their own executor.
+///
+/// Downloading a module is driven by the browser, so loads and preloads make progress without
+/// anything awaiting them. Only a few things run as futures of their own: loading the dependencies
+/// declared with [`declare_dependency`](crate::declare_dependency) before a module, fetching a
+/// [`LazyBlob`](crate::LazyBlob) and calls started with
+/// [`LazyLoader::spawn_call`](crate::LazyLoader::spawn_call). Without a spawner they're spawned
+/// with `wasm_bindgen_futures::spawn_local` in the browser, while outside the browser there is no
+/// default executor and calls started with `spawn_call` are driven by awaiting their handle.
+///
+/// ```rust, ignore
+/// wasm_split::set_spawn_fn(|future| dioxus::prelude::spawn_forever(future));
+/// ```
+pub fn set_spawn_fn(spawn: impl Fn(Pin<Box<dyn Future<Output = ()>>>) + 'static) {
+    SPAWN_FN.set(Some(Rc::new(spawn)));
+}
+
+/// Spawn the future with the function set with [`set_spawn_fn`], or with `spawn_local` in the
+/// browser. Outside the browser without a spawner, the future is handed back to be driven by the
+/// caller.
+pub(crate) fn spawn(future: SpawnedFuture) -> Option<SpawnedFuture> {
+    if let Some(spawn) = SPAWN_FN.with_borrow(|spawn| spawn.clone()) {
+        spawn(future);
+        return None;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        wasm_bindgen_futures::spawn_local(future);
+        None
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    Some(future)
+}
+
+/// How severe a diagnostic handed to the logger set with [`set_logger`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Progress of the runtime, like a load starting or being retried
+    Debug,
+    /// Likely misuse of the runtime or a performance problem, like a module that is slow to
+    /// instantiate
+    Warn,
+    /// A load that failed for good, after using up its retries
+    Error,
+}
+
+/// Route every internal diagnostic of the runtime to `logger`, like loads starting, retries and
+/// failures, to send them to whatever logging the app uses without pulling in a logging framework.
+///
+/// The logger is called with the level and the message, which names the split function it's about.
+/// It's called in release builds as well, so filter by level to keep the noise down. Without a
+/// logger, debug and warning messages go to the browser console in debug builds only, and failures
+/// aren't logged since the glue already reports them to the console. Setting a logger replaces both
+/// that and the previous logger.
+///
+/// ```rust, ignore
+/// wasm_split::set_logger(|level, message| {
+///     if level >= wasm_split::LogLevel::Warn {
+///         my_telemetry::log(message);
+///     }
+/// });
+/// ```
+pub fn set_logger(logger: impl Fn(LogLevel, &str) + 'static) {
+    LOGGER.set(Some(Rc::new(logger)));
+}
+
+/// Hand a diagnostic to the logger set with [`set_logger`], or to the console in debug builds
+pub(crate) fn log(level: LogLevel, message: std::fmt::Arguments<'_>) {
+    if let Some(logger) = LOGGER.with_borrow(|logger| logger.clone()) {
+        return logger(level, &message.to_string());
+    }
+
+    #[cfg(all(debug_assertions, target_arch = "wasm32"))]
+    match level {
+        LogLevel::Debug => web_sys::console::debug_1(&message.to_string().into()),
+        LogLevel::Warn => web_sys::console::warn_1(&message.to_string().into()),
+        LogLevel::Error => {}
+    }
+
+    #[cfg(not(all(debug_assertions, target_arch = "wasm32")))]
+    let _ = (level, message);
+}
+
+/// Set how long cancelling a preload scheduled with
+/// [`LazyLoader::preload_on_idle`](crate::LazyLoader::preload_on_idle) waits before it takes effect.
+///
+/// If the preload is requested again within this window, like when the user rapidly hovers on and
+/// off a prefetch trigger, the scheduled preload is kept instead of being cancelled and scheduled
+/// again. Loads are already deduplicated, so once a module's download has started, any further
+/// preload or load reuses it whether or not it has finished, and the debounce only matters for
+/// preloads that haven't started yet.
+///
+/// Defaults to zero, which cancels preloads right away.
+pub fn set_preload_debounce(debounce: Duration) {
+    PRELOAD_DEBOUNCE.set(debounce);
+}
+
+pub(crate) fn preload_debounce() -> Duration {
+    PRELOAD_DEBOUNCE.get()
+}
+
+/// Warn about every module whose instantiation blocks the main thread for longer than the threshold.
+///
+/// Instantiating a module runs synchronously on the main thread, so a module that takes long to
+/// instantiate causes jank. The warning names the split function whose module was slow, using the
+/// instantiate phase of its [`LoadTiming`](crate::LoadTiming). There is no threshold by default.
+pub fn set_instantiate_warn_threshold(threshold: Duration) {
+    INSTANTIATE_WARN_THRESHOLD.set(Some(threshold));
+}
+
+pub(crate) fn instantiate_warn_threshold() -> Option<Duration> {
+    INSTANTIATE_WARN_THRESHOLD.get()
+}
+
+/// Fail every load whose module takes longer than `timeout` to download with
+/// [`SplitLoaderError::TimedOut`](crate::SplitLoaderError::TimedOut), as a safety net so that
+/// nothing waits on a hanging request forever, including the lazy components suspended on it.
+///
+/// The timeout set on a loader with [`LoaderConfig::timeout`](crate::LoaderConfig::timeout) takes
+/// precedence over the default, so a loader with a large module can be given a longer one. There
+/// is no default timeout to begin with, and a timeout of zero removes it again.
+pub fn set_default_load_timeout(timeout: Duration) {
+    DEFAULT_LOAD_TIMEOUT.set((!timeout.is_zero()).then_some(timeout));
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn default_load_timeout() -> Option<Duration> {
+    DEFAULT_LOAD_TIMEOUT.get()
+}
+
+/// Give up on every loader whose module failed to load `threshold` times in this session, so a
+/// chunk that keeps failing stops costing the user more network requests.
+///
+/// Each load that fails after using up the retries of its loader counts once, except for loads
+/// that were aborted or refused for lack of consent. Once a loader has failed `threshold` times it
+/// is latched: it stays failed for the rest of the session, and neither
+/// [`LazyLoader::rearm`](crate::LazyLoader::rearm) nor the automatic rearming of
+/// [`enable_retry_on_visible`](crate::enable_retry_on_visible) and
+/// [`grant_consent`](crate::grant_consent) loads it again. Calls go to the loader's fallback if it
+/// has one, or return the error of its last load. Loaders aren't latched by default, and a
+/// threshold of zero turns latching off again. Lowering the threshold latches the loaders that
+/// already failed often enough.
+pub fn set_failure_threshold(threshold: u32) {
+    FAILURE_THRESHOLD.set((threshold > 0).then_some(threshold));
+}
+
+pub(crate) fn failure_threshold() -> Option<u32> {
+    FAILURE_THRESHOLD.get()
+}
+
+/// Keep every `#[component(lazy)]` component from showing the fallback of its suspense boundary
+/// until its module has been loading for `delay`, so loads faster than that, like of chunks in the
+/// HTTP cache, never flash a fallback.
+///
+/// Until the delay has passed, a lazy component whose module is still loading renders nothing
+/// instead of suspending, and it suspends as usual once the delay is over. The generated code reads
+/// the delay when the component first renders and races its load against a timer for it, so
+/// changing the delay only applies to components that render afterwards. There is no delay by
+/// default, and a delay of zero shows the fallback right away again.
+pub fn set_suspense_fallback_delay(delay: Duration) {
+    SUSPENSE_FALLBACK_DELAY.set((!delay.is_zero()).then_some(delay));
+}
+
+/// The delay set with [`set_suspense_fallback_delay`], read by the code `#[component(lazy)]`
+/// generates
+#[doc(hidden)]
+pub fn suspense_fallback_delay() -> Option<Duration> {
+    SUSPENSE_FALLBACK_DELAY.get()
+}
+
+/// Call the progress callbacks of loads at most once per `interval`, like the one passed to
+/// [`LazyLoader::load_with_progress`](crate::LazyLoader::load_with_progress), so fast networks
+/// don't re-render progress bars for every chunk of bytes that arrives.
+///
+/// Reports that come in before the interval passed since the last one are skipped, except for the
+/// first report with no bytes received and the last one: it's reported as soon as the last byte
+/// arrives when the server sent a `Content-Length`, and once the download finished otherwise, so
+/// the callback always ends on the full size of the module. The interval defaults to zero, which
+/// reports every chunk of bytes.
+pub fn set_progress_throttle(interval: Duration) {
+    PROGRESS_THROTTLE.set(interval);
+    glue::set_option_number("progressThrottle", interval.as_secs_f64() * 1000.0);
+}
+
+/// Set the sizes in bytes of the split modules ahead of loading them, from a manifest written at
+/// build time, for [`deferred_bytes`](crate::deferred_bytes) to tell how much code splitting
+/// kept out of the initial bundle.
+///
+/// The map goes from the logical name of a module to its size, with the same names as for
+/// [`set_chunk_hashes`], like `module_0_Editor` or `chunk_0_app`. Whether the sizes are of the
+/// modules as they are on disk or as they go over the wire is up to the manifest. No sizes are
+/// known by default, and setting them again replaces the old ones.
+pub fn set_chunk_sizes(sizes: HashMap<String, u64>) {
+    CHUNK_SIZES.set(Some(sizes));
+}
+
+pub(crate) fn chunk_sizes() -> Option<HashMap<String, u64>> {
+    CHUNK_SIZES.with_borrow(|sizes| sizes.clone())
+}
+
+/// Panic when [`LazyLoader::call`](crate::LazyLoader::call) is called on a loader whose load was
+/// never started, instead of returning an error.
+///
+/// Calling a loader nobody loaded is almost always a missing `load().await`, so this turns it into
+/// a loud failure during development. The check only looks at whether the loader was ever started:
+/// a loader that is still loading or failed to load returns its error as usual, and so does one
+/// with a fallback set. Strict mode only applies to debug builds, release builds ignore it. It's
+/// off by default.
+pub fn strict_mode(enabled: bool) {
+    STRICT_MODE.set(enabled);
+}
+
+pub(crate) fn is_strict() -> bool {
+    cfg!(debug_assertions) && STRICT_MODE.get()
+}
+
+/// Whether the runtime was initialized, after which the global options can't be changed anymore
+#[cfg(feature = "signed-manifest")]
+pub(crate) fn is_initialized() -> bool {
+    INITIALIZED.get()
+}
+
+/// A snapshot of the global options, returned by [`current_config`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct GlobalConfig {
+    /// The base URL set with [`set_chunk_base_url`]
+    pub chunk_base_url: Option<String>,
+
+    /// Whether a custom transport was set with [`set_transport`]
+    pub custom_transport: bool,
+
+    /// Whether a url rewriter was set with [`set_url_rewriter`]
+    pub url_rewriter: bool,
+
+    /// Whether a correlation id provider was set with [`set_correlation_id_provider`]
+    pub correlation_id_provider: bool,
+
+    /// The header set with [`set_correlation_header`]
+    pub correlation_header: Option<String>,
+
+    /// Whether shared imports were set with `set_shared_imports`
+    pub shared_imports: bool,
+
+    /// The limit set with [`set_max_chunk_bytes`]
+    pub max_chunk_bytes: Option<u64>,
+
+    /// The type set with [`set_expected_mime`]
+    pub expected_mime: Option<String>,
+
+    /// The hashes set with [`set_chunk_hashes`]
+    pub chunk_hashes: Option<HashMap<String, String>>,
+
+    /// Whether a manifest was verified with `set_signed_manifest`
+    pub signed_manifest: bool,
+
+    /// The sizes set with [`set_chunk_sizes`]
+    pub chunk_sizes: Option<HashMap<String, u64>>,
+
+    /// The scheduling set with [`set_callback_scheduling`]
+    pub callback_scheduling: CallbackScheduling,
+
+    /// The debounce set with [`set_preload_debounce`]
+    pub preload_debounce: Duration,
+
+    /// The threshold set with [`set_instantiate_warn_threshold`]
+    pub instantiate_warn_threshold: Option<Duration>,
+
+    /// The timeout set with [`set_default_load_timeout`]
+    pub default_load_timeout: Option<Duration>,
+
+    /// The threshold set with [`set_failure_threshold`]
+    pub failure_threshold: Option<u32>,
+
+    /// The delay set with [`set_suspense_fallback_delay`]
+    pub suspense_fallback_delay: Option<Duration>,
+
+    /// The interval set with [`set_progress_throttle`]
+    pub progress_throttle: Duration,
+
+    /// Whether a logger was set with [`set_logger`]
+    pub logger: bool,
+
+    /// Whether [`strict_mode`] is enabled
+    pub strict_mode: bool,
+
+    /// Whether the runtime was initialized, after which only the options that can be changed at any
+    /// time can still be changed
+    pub initialized: bool,
+}
+
+/// Get a snapshot of the global options that are currently set, like to log them at startup or to
+/// assert them in tests.
+pub fn current_config() -> GlobalConfig {
+    CONFIG.with_borrow(|config| GlobalConfig {
+        chunk_base_url: config.chunk_base_url.clone(),
+        custom_transport: config.transport.is_some(),
+        url_rewriter: config.url_rewriter.is_some(),
+        correlation_id_provider: config.correlation_id_provider.is_some(),
+        correlation_header: config.correlation_header.clone(),
+        #[cfg(target_arch = "wasm32")]
+        shared_imports: config.shared_imports.is_some(),
+        #[cfg(not(target_arch = "wasm32"))]
+        shared_imports: false,
+        max_chunk_bytes: config.max_chunk_bytes,
+        expected_mime: config.expected_mime.clone(),
+        chunk_hashes: config.chunk_hashes.clone(),
+        signed_manifest: config.chunk_integrity.is_some(),
+        chunk_sizes: chunk_sizes(),
+        callback_scheduling: CALLBACK_SCHEDULING.get(),
+        preload_debounce: PRELOAD_DEBOUNCE.get(),
+        instantiate_warn_threshold: INSTANTIATE_WARN_THRESHOLD.get(),
+        default_load_timeout: DEFAULT_LOAD_TIMEOUT.get(),
+        failure_threshold: FAILURE_THRESHOLD.get(),
+        suspense_fallback_delay: SUSPENSE_FALLBACK_DELAY.get(),
+        progress_throttle: PROGRESS_THROTTLE.get(),
+        logger: LOGGER.with_borrow(|logger| logger.is_some()),
+        strict_mode: STRICT_MODE.get(),
+        initialized: INITIALIZED.get(),
+    })
+}
+
+/// Initialize the runtime if it hasn't been initialized yet. Called before any module is loaded.
+pub(crate) fn ensure_initialized() {
+    if INITIALIZED.replace(true) {
+        return;
+    }
+
+    CONFIG.with_borrow(|config| {
+        if let Some(url) = &config.chunk_base_url {
+            glue::set_option("chunkBaseUrl", url);
+        }
+        if let Some(transport) = &config.transport {
+            glue::set_transport(transport.clone());
+        }
+        if let Some(rewriter) = &config.url_rewriter {
+            glue::set_url_rewriter(rewriter.clone());
+        }
+        if let Some(provider) = &config.correlation_id_provider {
+            let header = config.correlation_header.as_deref();
+            glue::set_correlation_id_provider(
+                provider.clone(),
+                header.unwrap_or("X-Correlation-Id"),
+            );
+        }
+        if let Some(max) = config.max_chunk_bytes {
+            glue::set_option_number("maxChunkBytes", max as f64);
+        }
+        if let Some(mime) = &config.expected_mime {
+            glue::set_option("expectedMime", mime);
+        }
+        if let Some(hashes) = &config.chunk_hashes {
+            glue::set_option_map("chunkHashes", hashes);
+        }
+        if let Some(integrity) = &config.chunk_integrity {
+            glue::set_option_map("chunkIntegrity", integrity);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(imports) = &config.shared_imports {
+            glue::set_option_value("sharedImports", imports);
+        }
+    });
+}
+
+fn configure(setter: &str, f: impl FnOnce(&mut Config)) {
+    if INITIALIZED.get() {
+        warn_log(format_args!(
+            "`{setter}` was called after wasm-split was initialized and will be ignored"
+        ));
+        return;
+    }
+
+    CONFIG.with_borrow_mut(f);
+}
@@ -0,0 +1,105 @@
+//! Holding back splits until the user consented to what they do, like loading analytics only
+//! after the user opted in.
+
+use crate::{LazyLoader, SplitLoader, SplitLoaderError};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+thread_local! {
+    /// The gated loaders with the category each one is gated on, by the address of the loader
+    static GATES: RefCell<HashMap<*const SplitLoader, (Rc<SplitLoader>, &'static str)>> = RefCell::new(HashMap::new());
+    static GRANTED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// A category of consent that loaders can be gated on with [`LazyLoader::gate`], like
+/// `"analytics"` or `"marketing"`.
+///
+/// ```rust, ignore
+/// static ANALYTICS: ConsentToken = ConsentToken::new("analytics");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConsentToken {
+    category: &'static str,
+}
+
+impl ConsentToken {
+    /// Create a token for the consent category with the given name
+    pub const fn new(category: &'static str) -> Self {
+        Self { category }
+    }
+
+    /// The name of the consent category
+    pub fn category(&self) -> &'static str {
+        self.category
+    }
+}
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Refuse to load this loader's module until consent for the category of `consent` is granted
+    /// with [`grant_consent`].
+    ///
+    /// Until then, loading it fails with [`SplitLoaderError::ConsentRequired`] and
+    /// [`LazyLoader::call`] falls back to the loader's fallback, or returns that error. Every
+    /// loader is gated on at most one category and gating it again replaces the category, while
+    /// any number of loaders can be gated on the same category so that one grant unlocks all of
+    /// them. The gate only applies to this loader: the other split functions in the same module
+    /// have loaders of their own, and loading one of those loads the module without asking for
+    /// consent, so gate each of them. A module that has already loaded stays loaded, and
+    /// loaders that were never split out can't be gated. [`load_critical`](crate::load_critical)
+    /// and the prefetches that go straight to the glue load modules by name and aren't gated, so
+    /// don't use them for gated splits.
+    pub fn gate(&'static self, consent: &ConsentToken) {
+        self.key.with(|inner| {
+            if inner.loader.load.is_some() {
+                GATES.with_borrow_mut(|gates| {
+                    gates.insert(
+                        Rc::as_ptr(&inner.loader),
+                        (inner.loader.clone(), consent.category),
+                    )
+                });
+            }
+        })
+    }
+}
+
+/// Grant consent for the category with the given name, letting the loaders gated on it load.
+///
+/// Loaders whose load was refused go back to not being loaded like with
+/// [`LazyLoader::rearm`](crate::LazyLoader::rearm), so the next load fetches their modules. Consent
+/// can't be revoked, since a module can't be unloaded once it has run.
+pub fn grant_consent(category: &str) {
+    GRANTED.with_borrow_mut(|granted| granted.insert(category.to_string()));
+
+    // Gated loaders are kept along with their gate, so this works without the registry
+    let gated = GATES.with_borrow(|gates| {
+        gates
+            .values()
+            .map(|(loader, _)| loader.clone())
+            .collect::<Vec<_>>()
+    });
+    for loader in gated {
+        let refused = matches!(
+            *loader.error.borrow(),
+            Some(SplitLoaderError::ConsentRequired)
+        );
+        if refused && check(&loader).is_none() {
+            loader.rearm();
+        }
+    }
+}
+
+/// Get the error for loading or calling into the loader without the consent it is gated on, if any
+pub(crate) fn check(loader: &SplitLoader) -> Option<SplitLoaderError> {
+    let category = GATES.with_borrow(|gates| {
+        gates
+            .get(&(loader as *const SplitLoader))
+            .map(|(_, category)| *category)
+    })?;
+    match GRANTED.with_borrow(|granted| granted.contains(category)) {
+        true => None,
+        false => Some(SplitLoaderError::ConsentRequired),
+    }
+}
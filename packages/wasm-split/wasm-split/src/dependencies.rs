@@ -0,0 +1,93 @@
+//! Dependencies between split modules declared at runtime, like splits that all need a shared
+//! runtime split to be loaded first.
+
+use crate::{Result, SplitLoaderError};
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+    static DEPENDENCIES: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new());
+}
+
+/// Declare that the module of the split function `dependent` needs the module of `dependency` to
+/// be loaded first.
+///
+/// Starting the loader of `dependent` then loads `dependency`, along with anything it depends on,
+/// before fetching its own module, so the loader only completes once all of them have loaded. If a
+/// dependency fails to load, so does `dependent`. The names are the names of the split functions,
+/// like the names passed to [`load_critical`](crate::load_critical). A load handed a signal, a
+/// progress callback or a response keeps them for its own module, while its dependencies are
+/// loaded without them.
+///
+/// The dependencies have to form a graph without cycles, since a cycle could never finish loading.
+/// Declaring a dependency that would close a cycle, including a split depending on itself, returns
+/// [`SplitLoaderError::CyclicDependency`] with the cycle and leaves the graph unchanged.
+///
+/// ```rust, ignore
+/// wasm_split::declare_dependency("Editor", "EditorRuntime")?;
+/// wasm_split::declare_dependency("Preview", "EditorRuntime")?;
+/// ```
+pub fn declare_dependency(dependent: &str, dependency: &str) -> Result<()> {
+    DEPENDENCIES.with_borrow_mut(|graph| {
+        if let Some(mut cycle) = path(graph, dependency, dependent) {
+            cycle.insert(0, dependent.to_string());
+            return Err(SplitLoaderError::CyclicDependency(cycle.join(" -> ")));
+        }
+
+        let dependencies = graph.entry(dependent.to_string()).or_default();
+        if !dependencies.iter().any(|declared| declared == dependency) {
+            dependencies.push(dependency.to_string());
+        }
+        Ok(())
+    })
+}
+
+/// Find a path of dependencies from `from` to `to`, including both ends
+fn path(graph: &HashMap<String, Vec<String>>, from: &str, to: &str) -> Option<Vec<String>> {
+    if from == to {
+        return Some(vec![to.to_string()]);
+    }
+
+    graph.get(from)?.iter().find_map(|next| {
+        let mut path = path(graph, next, to)?;
+        path.insert(0, from.to_string());
+        Some(path)
+    })
+}
+
+/// Get every split function with declared dependencies along with its dependencies, sorted by name
+#[cfg(feature = "registry")]
+pub(crate) fn graph() -> Vec<(String, Vec<String>)> {
+    let mut graph = DEPENDENCIES.with_borrow(|graph| {
+        graph
+            .iter()
+            .map(|(dependent, dependencies)| (dependent.clone(), dependencies.clone()))
+            .collect::<Vec<_>>()
+    });
+    graph.sort();
+    graph
+}
+
+/// Check whether any dependencies were declared for the split function with the given name
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn has_dependencies(name: &str) -> bool {
+    DEPENDENCIES.with_borrow(|graph| graph.contains_key(name))
+}
+
+/// Load the dependencies of the split function with the given name, each after its own
+/// dependencies
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load_dependencies(
+    name: &str,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>>>> {
+    let dependencies =
+        DEPENDENCIES.with_borrow(|graph| graph.get(name).cloned().unwrap_or_default());
+
+    Box::pin(async move {
+        futures_util::future::try_join_all(dependencies.iter().map(|dependency| async move {
+            load_dependencies(dependency).await?;
+            crate::load_critical(&[dependency]).await
+        }))
+        .await
+        .map(|_| ())
+    })
+}
@@ -0,0 +1,160 @@
+//! Events about loaders starting and finishing their loads, for orchestrating loads without holding
+//! on to the loaders themselves.
+
+use crate::{glue, LazyLoader};
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::{Rc, Weak},
+    task::{Context, Poll, Waker},
+};
+
+/// Something that happened to a loader
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadEvent {
+    /// The loader of the split function with this name started loading its module. Every retry
+    /// starts the load again.
+    Started { name: &'static str },
+
+    /// The loader of the split function with this name finished loading, successfully or not.
+    /// `cache_hit` is whether its module came from the HTTP cache, as described in
+    /// [`LazyLoader::was_cache_hit`](crate::LazyLoader::was_cache_hit).
+    Completed {
+        name: &'static str,
+        loaded: bool,
+        cache_hit: Option<bool>,
+    },
+
+    /// A `#[component(lazy)]` component that suspended because the module of the split function
+    /// with this name wasn't loaded yet resumed after `duration`, measured from when it first
+    /// rendered until its module finished loading, successfully or not. This is how long the user
+    /// waited, including any time the load was already in flight before the component rendered.
+    SuspenseResolved {
+        name: &'static str,
+        duration: std::time::Duration,
+    },
+}
+
+impl LoadEvent {
+    /// The name of the split function whose loader this event is about
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoadEvent::Started { name }
+            | LoadEvent::Completed { name, .. }
+            | LoadEvent::SuspenseResolved { name, .. } => name,
+        }
+    }
+}
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Load the lazy loader like [`LazyLoader::load`] for a component that suspends until its module
+    /// has loaded, emitting [`LoadEvent::SuspenseResolved`] with how long it was suspended.
+    ///
+    /// This is what `#[component(lazy)]` awaits. Nothing is emitted if the module had already
+    /// loaded, since the component never suspended.
+    pub async fn load_suspended(&'static self) -> bool {
+        if self.is_loaded() {
+            return true;
+        }
+
+        let start = glue::now();
+        let loaded = self.load().await;
+        let elapsed = (glue::now() - start).max(0.0);
+        emit(LoadEvent::SuspenseResolved {
+            name: self.key.with(|inner| inner.loader.name),
+            duration: std::time::Duration::from_secs_f64(elapsed / 1000.0),
+        });
+        loaded
+    }
+}
+
+/// Wait for the fallback delay of a `#[component(lazy)]` component that read `delay` from
+/// [`set_suspense_fallback_delay`](crate::set_suspense_fallback_delay) when it first rendered
+#[doc(hidden)]
+pub async fn suspense_fallback_delay_elapsed(delay: Option<std::time::Duration>) {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(delay) = delay {
+        crate::sleep(delay).await;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = delay;
+}
+
+/// A task waiting in [`next_event`] for an event matching its predicate
+struct Waiter {
+    predicate: Box<dyn Fn(&LoadEvent) -> bool>,
+    event: RefCell<Option<LoadEvent>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+thread_local! {
+    static WAITERS: RefCell<Vec<Weak<Waiter>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Wait for the next load event on this thread that satisfies `predicate`, like waiting for a
+/// split to finish loading without a reference to its loader:
+///
+/// ```rust, ignore
+/// let event = wasm_split::next_event(|event| {
+///     matches!(event, LoadEvent::Completed { name: "Editor", .. })
+/// })
+/// .await;
+/// ```
+///
+/// Only events that fire after this is called count, even if a matching event already happened
+/// before, so this waits for the next one. The predicate is registered right away rather than when
+/// the future is first polled, so events in between aren't missed. Dropping the future stops
+/// waiting.
+pub fn next_event(predicate: impl Fn(&LoadEvent) -> bool + 'static) -> NextEvent {
+    let waiter = Rc::new(Waiter {
+        predicate: Box::new(predicate),
+        event: RefCell::new(None),
+        waker: RefCell::new(None),
+    });
+    WAITERS.with_borrow_mut(|waiters| waiters.push(Rc::downgrade(&waiter)));
+    NextEvent { waiter }
+}
+
+/// The future returned by [`next_event`]
+pub struct NextEvent {
+    waiter: Rc<Waiter>,
+}
+
+impl Future for NextEvent {
+    type Output = LoadEvent;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(event) = self.waiter.event.take() {
+            return Poll::Ready(event);
+        }
+        self.waiter.waker.replace(Some(cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+/// Hand an event to every task waiting for one like it
+pub(crate) fn emit(event: LoadEvent) {
+    // Take the waiters out so predicates can wait for further events themselves
+    let waiters = WAITERS.take();
+    let mut remaining = Vec::with_capacity(waiters.len());
+    for waiter in waiters {
+        let Some(strong) = waiter.upgrade() else {
+            continue;
+        };
+        if (strong.predicate)(&event) {
+            strong.event.replace(Some(event.clone()));
+            if let Some(waker) = strong.waker.take() {
+                waker.wake();
+            }
+        } else {
+            remaining.push(waiter);
+        }
+    }
+    WAITERS.with_borrow_mut(|waiters| {
+        remaining.append(waiters);
+        *waiters = remaining;
+    });
+}
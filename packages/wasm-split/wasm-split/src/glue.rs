@@ -0,0 +1,736 @@
+//! Bindings to the `__wasm_split.js` glue that downloads and instantiates the split modules.
+//!
+//! Options are handed to the glue through the `window.__wasm_split_options` object which the glue
+//! reads whenever it loads a module. Once a module is loaded, the glue leaves a report about the
+//! load in `window.__wasm_split_report` right before calling back into the loader. Outside the
+//! browser there is no glue, so these are no-ops.
+
+#[cfg(target_arch = "wasm32")]
+use crate::{LoadTiming, SplitLoaderError};
+
+/// The report the glue leaves behind for the loader whose module it just loaded.
+#[derive(Default)]
+pub(crate) struct LoadReport {
+    pub(crate) timing: Option<crate::LoadTiming>,
+    pub(crate) memory_delta: Option<u64>,
+    pub(crate) size: Option<u64>,
+    pub(crate) cache_hit: Option<bool>,
+    pub(crate) exports: Option<Vec<String>>,
+    /// The name the module was registered with in the glue, like `module_0_Editor`
+    #[cfg(feature = "registry")]
+    pub(crate) chunk: Option<String>,
+    pub(crate) error: Option<crate::SplitLoaderError>,
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) module: Option<js_sys::WebAssembly::Module>,
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_option(key: &str, value: &str) {
+    set_option_value(key, &wasm_bindgen::JsValue::from_str(value));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_option(_key: &str, _value: &str) {}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_option_number(key: &str, value: f64) {
+    set_option_value(key, &wasm_bindgen::JsValue::from_f64(value));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_option_number(_key: &str, _value: f64) {}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_option_map(key: &str, map: &std::collections::HashMap<String, String>) {
+    use wasm_bindgen::JsValue;
+
+    let object = js_sys::Object::new();
+    for (key, value) in map {
+        _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &JsValue::from_str(value));
+    }
+    set_option_value(key, &object);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_option_map(_key: &str, _map: &std::collections::HashMap<String, String>) {}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_option_value(key: &str, value: &wasm_bindgen::JsValue) {
+    use js_sys::{Object, Reflect};
+    use wasm_bindgen::JsValue;
+
+    let global = js_sys::global();
+    let options_key = JsValue::from_str("__wasm_split_options");
+    let options = match Reflect::get(&global, &options_key) {
+        Ok(options) if options.is_object() => options,
+        _ => {
+            let options = JsValue::from(Object::new());
+            _ = Reflect::set(&global, &options_key, &options);
+            options
+        }
+    };
+
+    _ = Reflect::set(&options, &JsValue::from_str(key), value);
+}
+
+/// Hand a value to the glue for only the load that `start` kicks off. The glue takes it
+/// synchronously when the load starts, so it is cleared again right after.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn with_load_option(key: &str, value: &wasm_bindgen::JsValue, start: impl FnOnce()) {
+    set_option_value(key, value);
+    start();
+    set_option_value(key, &wasm_bindgen::JsValue::UNDEFINED);
+}
+
+/// Hand several values to the glue for only the load that `start` kicks off, like
+/// `with_load_option`
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn with_load_options(
+    options: &[(&'static str, wasm_bindgen::JsValue)],
+    start: impl FnOnce(),
+) {
+    for (key, value) in options {
+        set_option_value(key, value);
+    }
+    start();
+    for (key, _) in options {
+        set_option_value(key, &wasm_bindgen::JsValue::UNDEFINED);
+    }
+}
+
+/// Hand a loader's policies to the glue for only the load that `start` kicks off
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn with_policy(policy: &crate::policy::LoaderPolicy, start: impl FnOnce()) {
+    use crate::LoadPriority;
+    use wasm_bindgen::JsValue;
+
+    let priority = match policy.priority {
+        LoadPriority::Auto => "auto",
+        LoadPriority::High => "high",
+        LoadPriority::Low => "low",
+    };
+    let timeout = match policy.timeout.or_else(crate::config::default_load_timeout) {
+        Some(timeout) => JsValue::from_f64(timeout.as_secs_f64() * 1000.0),
+        None => JsValue::UNDEFINED,
+    };
+    let mirrors = policy
+        .mirrors
+        .iter()
+        .map(|mirror| JsValue::from_str(mirror))
+        .collect::<js_sys::Array>();
+
+    with_load_option("timeout", &timeout, || {
+        with_load_option("priority", &priority.into(), || {
+            with_load_option("mirrors", &mirrors, start)
+        })
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn with_policy(_policy: &crate::policy::LoaderPolicy, start: impl FnOnce()) {
+    start()
+}
+
+/// Hand the transport to the glue as an async function from the url of a module to its bytes
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_transport(transport: std::rc::Rc<dyn crate::ChunkTransport>) {
+    use wasm_bindgen::{closure::Closure, JsValue};
+
+    let fetch = Closure::<dyn Fn(String) -> js_sys::Promise>::new(move |url: String| {
+        let transport = transport.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let bytes = transport
+                .fetch(&url)
+                .await
+                .map_err(|err| JsValue::from_str(&err.to_string()))?;
+            Ok(js_sys::Uint8Array::from(bytes.as_slice()).into())
+        })
+    });
+
+    // The transport lives for the rest of the program
+    set_option_value("transport", fetch.as_ref());
+    fetch.forget();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_transport(_transport: std::rc::Rc<dyn crate::ChunkTransport>) {}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_url_rewriter(rewriter: crate::config::UrlRewriter) {
+    use wasm_bindgen::closure::Closure;
+
+    let rewrite = Closure::<dyn Fn(String) -> String>::new(move |url: String| rewriter(&url));
+
+    // The rewriter lives for the rest of the program
+    set_option_value("urlRewriter", rewrite.as_ref());
+    rewrite.forget();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_url_rewriter(_rewriter: crate::config::UrlRewriter) {}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_correlation_id_provider(
+    provider: crate::config::CorrelationIdProvider,
+    header: &str,
+) {
+    use wasm_bindgen::closure::Closure;
+
+    let provide = Closure::<dyn Fn() -> String>::new(move || provider());
+
+    // The provider lives for the rest of the program
+    set_option_value("correlationId", provide.as_ref());
+    set_option("correlationHeader", header);
+    provide.forget();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_correlation_id_provider(
+    _provider: crate::config::CorrelationIdProvider,
+    _header: &str,
+) {
+}
+
+/// Fetch the bytes at the url with the browser's `fetch`
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn fetch_bytes(url: &str) -> crate::Result<Vec<u8>> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let window = web_sys::window().ok_or(SplitLoaderError::FailedToLoad)?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|_| SplitLoaderError::FailedToLoad)?
+        .unchecked_into();
+    if !response.ok() {
+        return Err(SplitLoaderError::FailedToLoad);
+    }
+
+    let buffer = response
+        .array_buffer()
+        .map_err(|_| SplitLoaderError::FailedToLoad)?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|_| SplitLoaderError::FailedToLoad)?;
+
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn fetch_bytes(_url: &str) -> crate::Result<Vec<u8>> {
+    Err(crate::SplitLoaderError::FailedToLoad)
+}
+
+/// Fetch the bytes of a [`LazyBlob`](crate::LazyBlob) at the url the way the glue fetches modules
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn fetch_blob(url: &str) -> crate::Result<Vec<u8>> {
+    use js_sys::{Function, Promise, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    let fetch = Reflect::get(
+        &js_sys::global(),
+        &JsValue::from_str("__wasm_split_fetch_blob"),
+    )
+    .ok()
+    .and_then(|fetch| fetch.dyn_into::<Function>().ok())
+    .ok_or(SplitLoaderError::FailedToLoad)?;
+
+    // A blob has no policies of its own, but still gets the default timeout
+    let mut promise = None;
+    with_policy(&Default::default(), || {
+        promise = fetch
+            .call1(&JsValue::UNDEFINED, &JsValue::from_str(url))
+            .ok();
+    });
+    let promise = promise.ok_or(SplitLoaderError::FailedToLoad)?;
+    let result = JsFuture::from(promise.unchecked_into::<Promise>())
+        .await
+        .map_err(|_| SplitLoaderError::FailedToLoad)?;
+
+    if let Some(error) = Reflect::get(&result, &JsValue::from_str("error"))
+        .ok()
+        .and_then(|error| error.as_string())
+    {
+        return Err(load_error(&error, Vec::new));
+    }
+    let bytes = Reflect::get(&result, &JsValue::from_str("bytes"))
+        .map_err(|_| SplitLoaderError::FailedToLoad)?;
+    Ok(js_sys::Uint8Array::new(&bytes).to_vec())
+}
+
+/// The error for the kind of error the glue failed a fetch or load with, and the function to get
+/// the exports missing from a module that doesn't match its schema
+#[cfg(target_arch = "wasm32")]
+fn load_error(kind: &str, missing: impl FnOnce() -> Vec<String>) -> SplitLoaderError {
+    match kind {
+        "version-mismatch" => SplitLoaderError::VersionMismatch,
+        "aborted" => SplitLoaderError::Aborted,
+        "timeout" => SplitLoaderError::TimedOut,
+        "too-large" => SplitLoaderError::TooLarge,
+        "untrusted" => SplitLoaderError::Untrusted,
+        "schema-mismatch" => SplitLoaderError::SchemaMismatch(missing()),
+        _ => SplitLoaderError::FailedToLoad,
+    }
+}
+
+/// Check whether the module or shared chunk with the given file name has been instantiated, or
+/// `None` if the glue has no chunk with that name
+#[cfg(all(target_arch = "wasm32", feature = "registry"))]
+pub(crate) fn chunk_loaded(name: &str) -> Option<bool> {
+    use js_sys::Reflect;
+    use wasm_bindgen::JsValue;
+
+    let chunks = Reflect::get(&js_sys::global(), &JsValue::from_str("__wasm_split_chunks")).ok()?;
+    let load = Reflect::get(&chunks, &JsValue::from_str(name))
+        .ok()
+        .filter(|load| load.is_function())?;
+    Some(
+        Reflect::get(&load, &JsValue::from_str("loaded"))
+            .ok()
+            .and_then(|loaded| loaded.as_bool())
+            .unwrap_or(false),
+    )
+}
+
+/// Start loading every module of the split functions with the given names, returning a future
+/// for whether each module loaded successfully.
+///
+/// Nothing is loaded if no split function with one of the names exists, and that name is returned.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load_modules<'a>(
+    names: &[&'a str],
+) -> Result<Vec<wasm_bindgen_futures::JsFuture>, &'a str> {
+    use js_sys::{Array, Function, Promise, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let modules = Reflect::get(
+        &js_sys::global(),
+        &JsValue::from_str("__wasm_split_modules"),
+    )
+    .unwrap_or(JsValue::UNDEFINED);
+    let mut loaders = Vec::new();
+    for name in names {
+        match Reflect::get(&modules, &JsValue::from_str(name)) {
+            Ok(module_loaders) if module_loaders.is_array() => {
+                loaders.extend(Array::from(&module_loaders).iter())
+            }
+            _ => return Err(name),
+        }
+    }
+
+    Ok(loaders
+        .into_iter()
+        .map(|load| {
+            let promise = load
+                .unchecked_into::<Function>()
+                .call0(&JsValue::UNDEFINED)
+                .map(|promise| promise.unchecked_into::<Promise>())
+                .unwrap_or_else(|err| Promise::reject(&err));
+            promise.into()
+        })
+        .collect())
+}
+
+/// The names of every split function the glue registered a module for. The names are leaked the
+/// first time they're read, since they live as long as the program anyway.
+#[cfg(all(target_arch = "wasm32", feature = "registry"))]
+pub(crate) fn module_names() -> Vec<&'static str> {
+    use js_sys::{Object, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+
+    thread_local! {
+        static NAMES: std::cell::OnceCell<Vec<&'static str>> = const { std::cell::OnceCell::new() };
+    }
+
+    NAMES.with(|names| {
+        names
+            .get_or_init(|| {
+                let Ok(modules) = Reflect::get(
+                    &js_sys::global(),
+                    &JsValue::from_str("__wasm_split_modules"),
+                )
+                .and_then(|modules| modules.dyn_into::<Object>()) else {
+                    return Vec::new();
+                };
+                Object::keys(&modules)
+                    .iter()
+                    .filter_map(|name| name.as_string())
+                    .map(|name| &*Box::leak(name.into_boxed_str()))
+                    .collect()
+            })
+            .clone()
+    })
+}
+
+/// Start loading the chunks with the given names, returning a future for whether each one loaded,
+/// or `None` for names that aren't a chunk
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load_chunks(names: &[&str]) -> Vec<Option<wasm_bindgen_futures::JsFuture>> {
+    use js_sys::{Function, Promise, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let chunks = Reflect::get(&js_sys::global(), &JsValue::from_str("__wasm_split_chunks"))
+        .unwrap_or(JsValue::UNDEFINED);
+    names
+        .iter()
+        .map(|name| {
+            let load = Reflect::get(&chunks, &JsValue::from_str(name))
+                .ok()?
+                .dyn_into::<Function>()
+                .ok()?;
+            let promise = load
+                .call0(&JsValue::UNDEFINED)
+                .map(|promise| promise.unchecked_into::<Promise>())
+                .unwrap_or_else(|err| Promise::reject(&err));
+            Some(promise.into())
+        })
+        .collect()
+}
+
+/// Instantiate a newer build of the module of the split function with the given name from `url`,
+/// returning a future for whether it was swapped in, or `None` if no such module exists
+#[cfg(all(debug_assertions, target_arch = "wasm32"))]
+pub(crate) fn hot_swap(name: &str, url: &str) -> Option<wasm_bindgen_futures::JsFuture> {
+    use js_sys::{Array, Function, Promise, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let modules = Reflect::get(
+        &js_sys::global(),
+        &JsValue::from_str("__wasm_split_modules"),
+    )
+    .ok()?;
+    let module_loaders = Reflect::get(&modules, &JsValue::from_str(name)).ok()?;
+    if !module_loaders.is_array() {
+        return None;
+    }
+    let load = Array::from(&module_loaders).get(0);
+    let hot_swap = Reflect::get(&load, &JsValue::from_str("hotSwap"))
+        .ok()?
+        .dyn_into::<Function>()
+        .ok()?;
+    let promise = hot_swap
+        .call1(&load, &JsValue::from_str(url))
+        .ok()?
+        .unchecked_into::<Promise>();
+    Some(promise.into())
+}
+
+/// Take the report for the module that was just loaded. This must be called synchronously from the load callback.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn take_report() -> LoadReport {
+    use js_sys::Reflect;
+    use std::time::Duration;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let global = js_sys::global();
+    let report_key = JsValue::from_str("__wasm_split_report");
+    let Ok(report) = Reflect::get(&global, &report_key) else {
+        return LoadReport::default();
+    };
+    if !report.is_object() {
+        return LoadReport::default();
+    }
+    _ = Reflect::delete_property(&global, &report_key);
+
+    let duration = |key: &str| {
+        Reflect::get(&report, &JsValue::from_str(key))
+            .ok()
+            .and_then(|value| value.as_f64())
+            .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+    };
+
+    let bytes = |key: &str| {
+        Reflect::get(&report, &JsValue::from_str(key))
+            .ok()
+            .and_then(|value| value.as_f64())
+            .map(|bytes| bytes as u64)
+    };
+
+    let error = Reflect::get(&report, &JsValue::from_str("error"))
+        .ok()
+        .and_then(|error| error.as_string());
+    let strings = |key: &str| {
+        Reflect::get(&report, &JsValue::from_str(key))
+            .ok()
+            .filter(|strings| strings.is_array())
+            .map(|strings| {
+                js_sys::Array::from(&strings)
+                    .iter()
+                    .filter_map(|string| string.as_string())
+                    .collect::<Vec<_>>()
+            })
+    };
+
+    if let Some(error) = error {
+        return LoadReport {
+            timing: None,
+            memory_delta: None,
+            size: None,
+            cache_hit: None,
+            exports: None,
+            #[cfg(feature = "registry")]
+            chunk: None,
+            error: Some(load_error(&error, || {
+                strings("missing").unwrap_or_default()
+            })),
+            module: None,
+        };
+    }
+
+    LoadReport {
+        timing: Some(LoadTiming {
+            fetch: duration("fetch").unwrap_or_default(),
+            compile: duration("compile").unwrap_or_default(),
+            instantiate: duration("instantiate").unwrap_or_default(),
+        }),
+        memory_delta: bytes("memory"),
+        size: bytes("size"),
+        cache_hit: Reflect::get(&report, &JsValue::from_str("cacheHit"))
+            .ok()
+            .and_then(|value| value.as_bool()),
+        exports: strings("exports"),
+        #[cfg(feature = "registry")]
+        chunk: Reflect::get(&report, &JsValue::from_str("chunk"))
+            .ok()
+            .and_then(|chunk| chunk.as_string()),
+        error: None,
+        module: Reflect::get(&report, &JsValue::from_str("module"))
+            .ok()
+            .and_then(|module| module.dyn_into().ok()),
+    }
+}
+
+/// Get the bytes of the first custom section of the module with the given name
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn custom_section(module: &js_sys::WebAssembly::Module, name: &str) -> Option<Vec<u8>> {
+    let section = js_sys::WebAssembly::Module::custom_sections(module, name).get(0);
+    if section.is_undefined() {
+        return None;
+    }
+    Some(js_sys::Uint8Array::new(&section).to_vec())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn take_report() -> LoadReport {
+    LoadReport::default()
+}
+
+/// Wake the task on the next animation frame, falling back to waking it immediately if there's no window.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn wake_on_next_frame(waker: std::task::Waker) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let Some(window) = web_sys::window() else {
+        return waker.wake();
+    };
+
+    let callback = Closure::once_into_js(move || waker.wake());
+    _ = window.request_animation_frame(callback.unchecked_ref());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn wake_on_next_frame(waker: std::task::Waker) {
+    waker.wake()
+}
+
+/// Run the callback once the browser is idle, returning the handle of the idle callback.
+///
+/// Returns `None` without running the callback if idle callbacks aren't supported.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn request_idle_callback(f: impl FnOnce() + 'static) -> Option<u32> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let callback = Closure::once_into_js(f);
+    web_sys::window()?
+        .request_idle_callback(callback.unchecked_ref())
+        .ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn request_idle_callback(_f: impl FnOnce() + 'static) -> Option<u32> {
+    None
+}
+
+/// Call `f` with the path of the destination of every navigation the Navigation API starts,
+/// returning whether the API is available. Hash changes aren't reported since they stay on the page.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn on_navigate(f: impl Fn(String) + 'static) -> bool {
+    use js_sys::Reflect;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    let navigation = Reflect::get(&window, &JsValue::from_str("navigation")).unwrap_or_default();
+    let Ok(add_event_listener) = Reflect::get(&navigation, &JsValue::from_str("addEventListener"))
+        .and_then(|listener| listener.dyn_into::<js_sys::Function>())
+    else {
+        return false;
+    };
+
+    let listener = Closure::<dyn Fn(JsValue)>::new(move |event: JsValue| {
+        let get = |target: &JsValue, key: &str| {
+            Reflect::get(target, &JsValue::from_str(key)).unwrap_or_default()
+        };
+        if get(&event, "hashChange").is_truthy() {
+            return;
+        }
+        let Some(url) = get(&get(&event, "destination"), "url").as_string() else {
+            return;
+        };
+        if let Ok(url) = web_sys::Url::new(&url) {
+            f(url.pathname());
+        }
+    });
+    let installed = add_event_listener
+        .call2(
+            &navigation,
+            &JsValue::from_str("navigate"),
+            listener.as_ref(),
+        )
+        .is_ok();
+    listener.forget();
+    installed
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn on_navigate(_f: impl Fn(String) + 'static) -> bool {
+    false
+}
+
+/// Call `f` every time the page becomes visible again, returning whether the listener was installed
+#[cfg(all(target_arch = "wasm32", feature = "registry"))]
+pub(crate) fn on_visible(f: impl Fn() + 'static) -> bool {
+    use js_sys::Reflect;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    let document = Reflect::get(&window, &JsValue::from_str("document")).unwrap_or_default();
+    let Ok(add_event_listener) = Reflect::get(&document, &JsValue::from_str("addEventListener"))
+        .and_then(|listener| listener.dyn_into::<js_sys::Function>())
+    else {
+        return false;
+    };
+
+    let listener = Closure::<dyn Fn()>::new({
+        let document = document.clone();
+        move || {
+            let state = Reflect::get(&document, &JsValue::from_str("visibilityState"))
+                .ok()
+                .and_then(|state| state.as_string());
+            if state.as_deref() == Some("visible") {
+                f();
+            }
+        }
+    });
+    let installed = add_event_listener
+        .call2(
+            &document,
+            &JsValue::from_str("visibilitychange"),
+            listener.as_ref(),
+        )
+        .is_ok();
+    listener.forget();
+    installed
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "registry"))]
+pub(crate) fn on_visible(_f: impl Fn() + 'static) -> bool {
+    false
+}
+
+/// The current time in milliseconds from `performance.now()`, or from the clock set with
+/// `testing::set_clock`
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> f64 {
+    #[cfg(feature = "testing")]
+    if let Some(now) = crate::testing::now() {
+        return now;
+    }
+
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> f64 {
+    #[cfg(feature = "testing")]
+    if let Some(now) = crate::testing::now() {
+        return now;
+    }
+
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0
+}
+
+/// Get the `effectiveType` of `navigator.connection`, if the browser supports it
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn effective_connection_type() -> Option<String> {
+    use js_sys::Reflect;
+    use wasm_bindgen::JsValue;
+
+    let navigator = Reflect::get(&js_sys::global(), &JsValue::from_str("navigator")).ok()?;
+    let connection = Reflect::get(&navigator, &JsValue::from_str("connection")).ok()?;
+    if !connection.is_object() {
+        return None;
+    }
+    Reflect::get(&connection, &JsValue::from_str("effectiveType"))
+        .ok()?
+        .as_string()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn effective_connection_type() -> Option<String> {
+    None
+}
+
+/// Run the callback after the timeout, or right away if there's no window to schedule it on. With
+/// a clock set by `testing::set_clock`, the callback runs on its timers instead.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_timeout(timeout: std::time::Duration, f: impl FnOnce() + 'static) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    #[cfg(feature = "testing")]
+    let Some(f) = crate::testing::schedule(timeout, f) else {
+        return;
+    };
+
+    let Some(window) = web_sys::window() else {
+        return f();
+    };
+
+    // Longer timeouts would wrap around, and browsers cap the delay at `i32::MAX` ms anyway
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+    let callback = Closure::once_into_js(f);
+    _ = window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), timeout);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_timeout(timeout: std::time::Duration, f: impl FnOnce() + 'static) {
+    #[cfg(feature = "testing")]
+    let Some(f) = crate::testing::schedule(timeout, f) else {
+        return;
+    };
+
+    _ = timeout;
+    f()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn cancel_idle_callback(handle: u32) {
+    if let Some(window) = web_sys::window() {
+        window.cancel_idle_callback(handle);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn cancel_idle_callback(_handle: u32) {}
@@ -0,0 +1,301 @@
+use crate::LazyLoader;
+use futures_util::{stream::FuturesUnordered, Stream, StreamExt};
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+/// A loader with its argument and return types erased, letting loaders with different signatures be
+/// grouped together.
+pub trait AnyLoader: Sync {
+    /// The name of the split function this loader loads
+    fn name(&'static self) -> &'static str;
+
+    /// Start downloading the loader's module without waiting for it to finish
+    fn preload(&'static self);
+
+    /// Load the loader's module, resolving to whether it loaded successfully
+    fn load(&'static self) -> Pin<Box<dyn Future<Output = bool>>>;
+
+    /// Load the loader's module like [`LazyLoader::load_with_progress`]
+    fn load_with_progress(
+        &'static self,
+        on_progress: Box<dyn FnMut(u64, Option<u64>)>,
+    ) -> Pin<Box<dyn Future<Output = bool>>>;
+
+    /// Check whether the loader's module has loaded successfully, without starting a load
+    fn is_loaded(&'static self) -> bool;
+}
+
+impl<Args: 'static, Ret: 'static> AnyLoader for LazyLoader<Args, Ret> {
+    fn name(&'static self) -> &'static str {
+        self.key.with(|inner| inner.loader.name)
+    }
+
+    fn preload(&'static self) {
+        LazyLoader::preload(self)
+    }
+
+    fn load(&'static self) -> Pin<Box<dyn Future<Output = bool>>> {
+        Box::pin(LazyLoader::load(self))
+    }
+
+    fn load_with_progress(
+        &'static self,
+        on_progress: Box<dyn FnMut(u64, Option<u64>)>,
+    ) -> Pin<Box<dyn Future<Output = bool>>> {
+        Box::pin(LazyLoader::load_with_progress(self, on_progress))
+    }
+
+    fn is_loaded(&'static self) -> bool {
+        LazyLoader::is_loaded(self)
+    }
+}
+
+/// A group of loaders that together make up a feature, like all the splits of an editor.
+///
+/// ```rust, ignore
+/// static EDITOR: LoaderGroup = LoaderGroup::new(&[&TOOLBAR, &CANVAS]);
+///
+/// if !EDITOR.all_loaded() {
+///     EDITOR.load().await;
+/// }
+/// ```
+pub struct LoaderGroup {
+    loaders: &'static [&'static dyn AnyLoader],
+    fallback: Option<fn()>,
+}
+
+impl LoaderGroup {
+    /// Create a new group from a list of loaders
+    pub const fn new(loaders: &'static [&'static dyn AnyLoader]) -> Self {
+        Self {
+            loaders,
+            fallback: None,
+        }
+    }
+
+    /// Run this function instead of the feature from [`LoaderGroup::run`] whenever any loader in
+    /// the group isn't loaded, so a partially loaded feature degrades as a whole.
+    ///
+    /// ```rust, ignore
+    /// static EDITOR: LoaderGroup = LoaderGroup::new(&[&TOOLBAR, &CANVAS]).with_fallback(plain_textarea);
+    /// ```
+    pub const fn with_fallback(self, fallback: fn()) -> Self {
+        Self {
+            loaders: self.loaders,
+            fallback: Some(fallback),
+        }
+    }
+
+    /// Run the feature made up by this group's loaders, using the group's fallback if the feature
+    /// isn't fully loaded. This doesn't start any loads.
+    ///
+    /// A group only counts as loaded once every one of its loaders has loaded, so a group where
+    /// some loaders loaded and others failed or are still loading runs the fallback set with
+    /// [`LoaderGroup::with_fallback`] and `feature` never sees a mix of loaded and missing splits.
+    /// The fallbacks of the individual loaders aren't used in that case. Without a group fallback,
+    /// `feature` always runs and each of its calls into a missing split falls back to that
+    /// loader's own fallback, or returns its error. Returns whether `feature` ran.
+    pub fn run(&self, feature: impl FnOnce()) -> bool {
+        match self.fallback {
+            Some(fallback) if !self.all_loaded() => {
+                fallback();
+                false
+            }
+            _ => {
+                feature();
+                true
+            }
+        }
+    }
+
+    /// The loaders in this group
+    pub fn loaders(&self) -> &'static [&'static dyn AnyLoader] {
+        self.loaders
+    }
+
+    /// Load every loader in the group, returning whether all of them loaded successfully.
+    ///
+    /// All the modules are downloaded concurrently.
+    pub async fn load(&self) -> bool {
+        load_all_ordered(self.loaders)
+            .await
+            .into_iter()
+            .all(|loaded| loaded)
+    }
+
+    /// Load every loader in the group, returning `true` as soon as at least `n` of them have loaded
+    /// successfully, for features made up of redundant or optional splits that work without all of
+    /// them.
+    ///
+    /// This returns `false` as soon as so many loaders failed that fewer than `n` can still load,
+    /// and right away if the group has fewer than `n` loaders. Either way the loaders that weren't
+    /// needed aren't aborted: their modules keep loading in the background, so they are ready if
+    /// the feature uses them later. A quorum of `0` is always met.
+    pub async fn load_quorum(&self, n: usize) -> bool {
+        if n > self.loaders.len() {
+            return false;
+        }
+
+        let mut remaining = self.loaders.len();
+        let mut loaded = 0;
+        let mut completed = load_all_as_completed(self.loaders);
+        while loaded < n {
+            if loaded + remaining < n {
+                return false;
+            }
+            let Some((_, success)) = completed.next().await else {
+                return false;
+            };
+            remaining -= 1;
+            loaded += usize::from(success);
+        }
+        true
+    }
+
+    /// Load every loader in the group like [`LoaderGroup::load`], calling `on_progress` with the
+    /// bytes received and the total bytes summed across the group, for a single progress bar.
+    ///
+    /// Loaders that were already loaded count as done. The total is only known once every
+    /// downloading loader has reported its size. Until then, and whenever the server didn't send the
+    /// size of a module, `on_progress` is called with a total of `0` and the progress should be
+    /// shown as indeterminate. Loaders that finish without reporting, like ones that were already
+    /// loading, count as done once they finish.
+    pub async fn load_with_progress(&self, on_progress: impl FnMut(u64, u64) + 'static) -> bool {
+        let progress = Rc::new(RefCell::new(GroupProgress {
+            members: self
+                .loaders
+                .iter()
+                .map(|loader| (0, loader.is_loaded().then_some(0)))
+                .collect(),
+            on_progress: Box::new(on_progress),
+        }));
+
+        let futures = self
+            .loaders
+            .iter()
+            .enumerate()
+            .map(|(idx, loader)| {
+                let progress = progress.clone();
+                let load = loader.load_with_progress(Box::new({
+                    let progress = progress.clone();
+                    move |received, total| progress.borrow_mut().report(idx, received, total)
+                }));
+                Some(Box::pin(async move {
+                    let loaded = load.await;
+                    let mut progress = progress.borrow_mut();
+                    if let (received, None) = progress.members[idx] {
+                        progress.report(idx, received, Some(received));
+                    }
+                    loaded
+                }) as Pin<Box<dyn Future<Output = bool>>>)
+            })
+            .collect();
+
+        JoinOrdered {
+            results: vec![false; self.loaders.len()],
+            futures,
+        }
+        .await
+        .into_iter()
+        .all(|loaded| loaded)
+    }
+
+    /// Check whether every loader in the group has loaded successfully, without starting any loads
+    pub fn all_loaded(&self) -> bool {
+        self.loaders.iter().all(|loader| loader.is_loaded())
+    }
+
+    /// Check whether any loader in the group has loaded successfully, without starting any loads
+    pub fn any_loaded(&self) -> bool {
+        self.loaders.iter().any(|loader| loader.is_loaded())
+    }
+}
+
+/// The progress of every loader in a group, summed up for `LoaderGroup::load_with_progress`
+struct GroupProgress {
+    /// The bytes received and the total of every loader
+    members: Vec<(u64, Option<u64>)>,
+    on_progress: Box<dyn FnMut(u64, u64)>,
+}
+
+impl GroupProgress {
+    fn report(&mut self, idx: usize, received: u64, total: Option<u64>) {
+        self.members[idx] = (received, total);
+        let received = self.members.iter().map(|(received, _)| received).sum();
+        let total = self
+            .members
+            .iter()
+            .map(|(_, total)| *total)
+            .sum::<Option<u64>>()
+            .unwrap_or(0);
+        (self.on_progress)(received, total);
+    }
+}
+
+/// Load every loader concurrently, returning whether each one loaded successfully.
+///
+/// `result[i]` is always the result of `loaders[i]`, no matter which order the modules finish
+/// loading in.
+pub async fn load_all_ordered(loaders: &[&'static dyn AnyLoader]) -> Vec<bool> {
+    JoinOrdered {
+        futures: loaders.iter().map(|loader| Some(loader.load())).collect(),
+        results: vec![false; loaders.len()],
+    }
+    .await
+}
+
+/// Load every loader concurrently, yielding `(index, loaded)` for each loader in the order they finish.
+///
+/// This lets each feature render as soon as its module arrives instead of waiting for all of them.
+/// `index` is the index of the loader in `loaders`.
+///
+/// ```rust, ignore
+/// let mut completed = wasm_split::load_all_as_completed(&[&TOOLBAR, &CANVAS]);
+/// while let Some((idx, loaded)) = completed.next().await {
+///     reveal(idx, loaded);
+/// }
+/// ```
+pub fn load_all_as_completed(
+    loaders: &[&'static dyn AnyLoader],
+) -> impl Stream<Item = (usize, bool)> {
+    loaders
+        .iter()
+        .enumerate()
+        .map(|(idx, loader)| {
+            let load = loader.load();
+            async move { (idx, load.await) }
+        })
+        .collect::<FuturesUnordered<_>>()
+}
+
+/// Polls every load together, storing each result at the index of its loader
+struct JoinOrdered {
+    futures: Vec<Option<Pin<Box<dyn Future<Output = bool>>>>>,
+    results: Vec<bool>,
+}
+
+impl Future for JoinOrdered {
+    type Output = Vec<bool>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<bool>> {
+        let this = &mut *self;
+        for (future, result) in this.futures.iter_mut().zip(this.results.iter_mut()) {
+            if let Some(Poll::Ready(loaded)) =
+                future.as_mut().map(|future| future.as_mut().poll(cx))
+            {
+                *result = loaded;
+                *future = None;
+            }
+        }
+
+        match this.futures.iter().all(Option::is_none) {
+            true => Poll::Ready(std::mem::take(&mut this.results)),
+            false => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Callbacks the runtime invokes when something happens to a loader.
+
+use crate::SplitLoaderError;
+use std::{cell::RefCell, rc::Rc};
+
+/// A callback that receives the name of a loader
+type LoaderCallback = Rc<dyn Fn(&str)>;
+
+/// A callback that receives an error and the name of the loader it came from
+type ErrorCallback = Rc<dyn Fn(&SplitLoaderError, &str)>;
+
+thread_local! {
+    static ON_VERSION_MISMATCH: RefCell<Option<LoaderCallback>> = const { RefCell::new(None) };
+    static ERROR_HANDLER: RefCell<Option<ErrorCallback>> = const { RefCell::new(None) };
+    static GLOBAL_FALLBACK: RefCell<Option<LoaderCallback>> = const { RefCell::new(None) };
+}
+
+/// Set a callback that is invoked with the name of the loader whenever a split module turns out to be
+/// from a different build than the main module.
+///
+/// This usually means that the user is running a stale main bundle while the server already has
+/// the modules of a newer deploy, so it's a good place to prompt for a reload. The load itself
+/// fails and [`LazyLoader::call`](crate::LazyLoader::call) returns
+/// [`SplitLoaderError::VersionMismatch`](crate::SplitLoaderError::VersionMismatch).
+///
+/// The bundler embeds the id of the build in a `__wasm_split_build_id` custom section of every split
+/// module and writes the same id into the glue, which compares the two after compiling a module.
+pub fn on_version_mismatch(f: impl Fn(&str) + 'static) {
+    ON_VERSION_MISMATCH.set(Some(Rc::new(f)));
+}
+
+pub(crate) fn version_mismatch(name: &str) {
+    // Clone the callback out so it can replace itself without a double borrow
+    if let Some(f) = ON_VERSION_MISMATCH.with_borrow(|f| f.clone()) {
+        f(name)
+    }
+}
+
+/// Set a catch-all handler that is invoked with the error and the name of the loader whenever a
+/// module fails to load or [`LazyLoader::call`](crate::LazyLoader::call) fails.
+///
+/// This is meant for logging and telemetry. It doesn't change what the failing call returns, so
+/// errors can still be handled at each call site.
+pub fn set_error_handler(f: impl Fn(&SplitLoaderError, &str) + 'static) {
+    ERROR_HANDLER.set(Some(Rc::new(f)));
+}
+
+pub(crate) fn error(error: &SplitLoaderError, name: &str) {
+    if let Some(f) = ERROR_HANDLER.with_borrow(|f| f.clone()) {
+        f(error, name)
+    }
+}
+
+/// Set a last-resort callback that is invoked with the name of the loader whenever a module fails to
+/// load and the loader has no fallback of its own, like to show a generic "you're offline" message.
+///
+/// Unlike the [error handler](set_error_handler), which sees every error for logging, this is meant
+/// to trigger recovery UI for the failures nothing else handles. A loader with a fallback set with
+/// [`LoaderConfig::fallback`](crate::LoaderConfig::fallback) takes precedence and never reaches
+/// the global fallback, since its calls keep working. The global fallback runs once per failed load,
+/// after any retries, and not for every call to the failed loader.
+pub fn set_global_fallback(f: impl Fn(&str) + 'static) {
+    GLOBAL_FALLBACK.set(Some(Rc::new(f)));
+}
+
+pub(crate) fn global_fallback(name: &str) {
+    if let Some(f) = GLOBAL_FALLBACK.with_borrow(|f| f.clone()) {
+        f(name)
+    }
+}
@@ -0,0 +1,31 @@
+//! Counting the loads that are in flight, for graphing them over time.
+
+use std::cell::Cell;
+
+thread_local! {
+    static INFLIGHT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Get how many loaders on this thread are waiting for their module to load right now, like for
+/// graphing the loads in flight on a live dashboard.
+///
+/// The count is kept up to date as loaders move in and out of the pending state, so reading it
+/// doesn't scan anything and it works without the `registry` feature. It goes up when a load starts
+/// and down once it completes, whether it loaded or failed. Retries and restarts stay counted the
+/// whole time, so a load that's retried once counts as one load in flight until it gives up or
+/// succeeds. Loads queued with [`pause_loads`](crate::pause_loads) are pending as well, so they're
+/// counted while they wait for [`resume_loads`](crate::resume_loads). Modules loaded straight from
+/// the glue, like with [`load_critical`](crate::load_critical), don't go through a loader and
+/// aren't counted.
+pub fn inflight_count() -> usize {
+    INFLIGHT.get()
+}
+
+/// Count a loader that moved into or out of the pending state
+pub(crate) fn transition(was_pending: bool, is_pending: bool) {
+    match (was_pending, is_pending) {
+        (false, true) => INFLIGHT.set(INFLIGHT.get() + 1),
+        (true, false) => INFLIGHT.set(INFLIGHT.get() - 1),
+        _ => {}
+    }
+}
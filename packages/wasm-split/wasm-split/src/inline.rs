@@ -0,0 +1,89 @@
+//! Split modules inlined into the page as base64, for small modules that are needed right away and
+//! aren't worth the extra round-trip.
+
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+    static INLINE_CHUNKS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// Register the module of the split function `name` as base64, like a module inlined into the HTML,
+/// so its loader decodes and instantiates these bytes instead of fetching the module.
+///
+/// The base64 is only decoded when the loader starts, in a single pass straight into the bytes
+/// handed to the browser, and is dropped afterwards. Both the standard and the URL-safe alphabets
+/// are accepted, with or without padding. If the base64 is invalid, the module is fetched as usual.
+/// Only the loader's own load uses the inlined module, not [`load_critical`](crate::load_critical).
+///
+/// ```rust, ignore
+/// wasm_split::register_inline_chunk("Header", include_str!("header.wasm.b64"));
+/// ```
+pub fn register_inline_chunk(name: &str, base64: &str) {
+    INLINE_CHUNKS.with_borrow_mut(|chunks| chunks.insert(name.to_string(), base64.to_string()));
+}
+
+/// Hand the inlined module of the split function to the load that `start` kicks off, if one was
+/// registered
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn with_inline_chunk(name: &str, start: impl FnOnce()) {
+    let Some(base64) = INLINE_CHUNKS.with_borrow_mut(|chunks| chunks.remove(name)) else {
+        return start();
+    };
+
+    match decode(&base64).and_then(|bytes| response(&bytes)) {
+        Some(response) => crate::glue::with_load_option("response", &response, start),
+        None => {
+            crate::warn_log(format_args!(
+                "{name}: the inlined module isn't valid base64, fetching the module instead"
+            ));
+            start()
+        }
+    }
+}
+
+/// Wrap the bytes of a module in a response the glue can compile
+#[cfg(target_arch = "wasm32")]
+fn response(bytes: &[u8]) -> Option<web_sys::Response> {
+    use wasm_bindgen::JsValue;
+
+    let headers = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &headers,
+        &JsValue::from_str("Content-Type"),
+        &JsValue::from_str("application/wasm"),
+    )
+    .ok()?;
+    let init = web_sys::ResponseInit::new();
+    init.set_headers(&headers);
+
+    let body = js_sys::Uint8Array::from(bytes);
+    web_sys::Response::new_with_opt_buffer_source_and_init(Some(&body), &init).ok()
+}
+
+/// Decode base64 in either alphabet, ignoring whitespace and anything after the padding
+#[cfg(target_arch = "wasm32")]
+fn decode(base64: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(base64.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in base64
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .take_while(|byte| *byte != b'=')
+    {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6 | value as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
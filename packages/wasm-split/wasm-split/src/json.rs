@@ -0,0 +1,16 @@
+use crate::{LazyLoader, Result, SplitLoaderError};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::JsValue;
+
+impl<Args> LazyLoader<Args, JsValue> {
+    /// Call a split that returns a `JsValue` and deserialize the value with `serde-wasm-bindgen`.
+    ///
+    /// This is meant for splits that bridge to JS-heavy code. Errors from [`LazyLoader::call`] are
+    /// returned as is, and a value that doesn't match `T` returns [`SplitLoaderError::Deserialize`]
+    /// with the message of the deserialization error.
+    pub fn call_json<T: DeserializeOwned>(&'static self, args: Args) -> Result<T> {
+        let value = self.call(args)?;
+        serde_wasm_bindgen::from_value(value)
+            .map_err(|err| SplitLoaderError::Deserialize(err.to_string()))
+    }
+}
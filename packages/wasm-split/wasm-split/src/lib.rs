@@ -1,5 +1,5 @@
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     ffi::c_void,
     future::Future,
     pin::Pin,
@@ -8,7 +8,108 @@ use std::{
     thread::LocalKey,
 };
 
-pub use wasm_split_macro::{lazy_loader, wasm_split};
+pub use wasm_split_macro::{lazy_loader, wasm_split, LoaderRegistry};
+
+#[cfg(feature = "bench")]
+pub mod benchmark;
+mod blob;
+mod config;
+mod consent;
+mod dependencies;
+mod events;
+mod glue;
+mod group;
+mod hooks;
+mod inflight;
+mod inline;
+#[cfg(all(feature = "serde", target_arch = "wasm32"))]
+mod json;
+mod loaded;
+#[cfg(feature = "signed-manifest")]
+mod manifest;
+mod metadata;
+mod module_cache;
+mod network;
+mod out_param;
+mod pause;
+mod policy;
+mod predict;
+mod preload;
+#[cfg(feature = "dioxus")]
+mod reactive;
+mod recording;
+#[cfg(feature = "registry")]
+mod registry;
+mod schema;
+mod seed;
+mod spawn;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timing;
+mod transport;
+#[cfg(feature = "registry")]
+mod visibility;
+mod waterfall;
+
+pub use blob::{LazyBlob, LazyBlobState};
+#[cfg(target_arch = "wasm32")]
+pub use config::set_shared_imports;
+pub use config::{
+    current_config, init, set_callback_scheduling, set_chunk_base_url, set_chunk_hashes,
+    set_chunk_sizes, set_correlation_header, set_correlation_id_provider, set_default_load_timeout,
+    set_expected_mime, set_failure_threshold, set_instantiate_warn_threshold, set_logger,
+    set_max_chunk_bytes, set_preload_debounce, set_progress_throttle, set_spawn_fn,
+    set_suspense_fallback_delay, set_transport, set_url_rewriter, strict_mode,
+    suspense_fallback_delay, CallbackScheduling, GlobalConfig, LogLevel,
+};
+pub use consent::{grant_consent, ConsentToken};
+pub use dependencies::declare_dependency;
+pub use events::{next_event, suspense_fallback_delay_elapsed, LoadEvent, NextEvent};
+pub use group::{load_all_as_completed, load_all_ordered, AnyLoader, LoaderGroup};
+pub use hooks::{on_version_mismatch, set_error_handler, set_global_fallback};
+pub use inflight::inflight_count;
+pub use inline::register_inline_chunk;
+pub use loaded::LoadedLoader;
+#[cfg(feature = "signed-manifest")]
+pub use manifest::set_signed_manifest;
+pub use module_cache::set_module_cache_limit;
+pub use network::{network_quality, set_preload_on_slow_networks, NetworkQuality};
+pub use out_param::OutLoader;
+pub use pause::{pause_loads, resume_loads, set_chunk_priority, set_max_concurrent_loads};
+pub use policy::{LoadPriority, LoaderConfig};
+#[cfg(feature = "registry")]
+pub use predict::on_route_change;
+pub use predict::{
+    chunks_for_route, install_navigation_prefetch, predict_and_preload, register_route_chunks,
+};
+#[cfg(feature = "registry")]
+pub use preload::flush_preloads;
+pub use preload::{load_critical, load_sequence, preload_chunks, IdlePreload};
+#[cfg(target_arch = "wasm32")]
+pub use preload::{load_on_event, EventPreload};
+#[cfg(feature = "dioxus")]
+pub use reactive::{suspend_on, use_load_progress, use_load_state, use_loaded, LoadState};
+pub use recording::{start_recording, stop_recording, LoadRecord};
+#[cfg(all(feature = "registry", debug_assertions))]
+pub use registry::pending_loaders;
+#[cfg(feature = "registry")]
+pub use registry::{
+    all_loader_names, assert_all_split, deferred_bytes, ensure_loaded_by_name, export_graph_dot,
+    has_pending_work, loaded_chunk_count, unused_loaded_chunks,
+};
+#[cfg(all(feature = "registry", feature = "serde"))]
+pub use registry::{
+    diff_snapshots, registry_snapshot_json, ChunkDiff, LoaderSnapshot, SnapshotDiff, TimingSnapshot,
+};
+pub use schema::set_export_schema;
+pub use spawn::CallHandle;
+#[cfg(feature = "testing")]
+pub use testing::{dry_run, dry_run_log, mock_all_loaded, set_dry_run_result, DryRunEntry};
+pub use timing::LoadTiming;
+pub use transport::{ChunkTransport, HttpTransport};
+#[cfg(feature = "registry")]
+pub use visibility::enable_retry_on_visible;
+pub use waterfall::waterfall_warnings;
 
 pub type Result<T> = std::result::Result<T, SplitLoaderError>;
 
@@ -16,14 +117,111 @@ pub type Result<T> = std::result::Result<T, SplitLoaderError>;
 #[derive(Debug, Clone)]
 pub enum SplitLoaderError {
     FailedToLoad,
+    VersionMismatch,
+    /// No loader with this name has been registered
+    UnknownLoader(String),
+    /// The load was aborted with the signal passed to [`LazyLoader::load_with_signal`], or the call
+    /// with [`CallHandle::abort`]
+    Aborted,
+    /// Downloading the module took longer than the timeout set with [`LoaderConfig::timeout`]
+    TimedOut,
+    /// The module was larger than the limit set with [`set_max_chunk_bytes`]
+    TooLarge,
+    /// Declaring a dependency with [`declare_dependency`] would create this cycle of splits
+    CyclicDependency(String),
+    /// The value returned by the split couldn't be deserialized by `LazyLoader::call_json`
+    Deserialize(String),
+    /// The future returned by the split didn't finish within the timeout of `LazyLoader::call_with_timeout`
+    CallTimedOut,
+    /// The module is missing these exports required by [`set_export_schema`]
+    SchemaMismatch(Vec<String>),
+    /// The loader was marked as loaded by `mock_all_loaded`, so its split function can't be called
+    Mocked,
+    /// The loader is gated with [`LazyLoader::gate`] on consent that hasn't been granted with [`grant_consent`]
+    ConsentRequired,
+    /// The manifest passed to `set_signed_manifest` couldn't be verified, for this reason
+    InvalidManifest(String),
+    /// The module isn't in the manifest verified by `set_signed_manifest`, or its bytes don't match
+    /// the hash the manifest lists for it
+    Untrusted,
+    /// The loader was marked as permanently failed with [`LazyLoader::mark_failed`]
+    Disabled,
 }
 impl std::fmt::Display for SplitLoaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SplitLoaderError::FailedToLoad => write!(f, "Failed to load wasm-split module"),
+            SplitLoaderError::VersionMismatch => write!(
+                f,
+                "The wasm-split module is from a different build than the main module"
+            ),
+            SplitLoaderError::Aborted => write!(f, "The wasm-split module load was aborted"),
+            SplitLoaderError::TimedOut => write!(f, "The wasm-split module took too long to load"),
+            SplitLoaderError::TooLarge => write!(
+                f,
+                "The wasm-split module is larger than the maximum chunk size"
+            ),
+            SplitLoaderError::CallTimedOut => {
+                write!(f, "The wasm-split function took too long to finish")
+            }
+            SplitLoaderError::SchemaMismatch(missing) => write!(
+                f,
+                "The wasm-split module is missing the exports {}",
+                missing.join(", ")
+            ),
+            SplitLoaderError::Mocked => write!(
+                f,
+                "The wasm-split module was mocked as loaded and has no fallback to call"
+            ),
+            SplitLoaderError::ConsentRequired => write!(
+                f,
+                "The wasm-split module can't be loaded until the user consents to it"
+            ),
+            SplitLoaderError::InvalidManifest(reason) => {
+                write!(f, "The signed wasm-split manifest is invalid: {reason}")
+            }
+            SplitLoaderError::Untrusted => {
+                write!(f, "The wasm-split module doesn't match the signed manifest")
+            }
+            SplitLoaderError::Disabled => {
+                write!(f, "The wasm-split module was marked as permanently failed")
+            }
+            SplitLoaderError::Deserialize(err) => write!(
+                f,
+                "Failed to deserialize the value returned by the wasm-split module: {err}"
+            ),
+            SplitLoaderError::CyclicDependency(cycle) => {
+                write!(
+                    f,
+                    "Declaring this wasm-split dependency would create a cycle: {cycle}"
+                )
+            }
+            SplitLoaderError::UnknownLoader(name) => {
+                write!(f, "No wasm-split loader named `{name}` has been registered")
+            }
         }
     }
 }
+impl std::error::Error for SplitLoaderError {}
+
+impl SplitLoaderError {
+    /// Whether loading the module again could succeed after failing with this error.
+    ///
+    /// Retrying can't fix a module from another build, with the wrong exports or that isn't
+    /// trusted, a load that was aborted on purpose or lacks consent, or a loader that was marked as
+    /// failed.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            SplitLoaderError::VersionMismatch
+                | SplitLoaderError::Aborted
+                | SplitLoaderError::SchemaMismatch(_)
+                | SplitLoaderError::ConsentRequired
+                | SplitLoaderError::Untrusted
+                | SplitLoaderError::Disabled
+        )
+    }
+}
 
 /// A lazy loader that can be used to load a function from a split out `.wasm` file.
 ///
@@ -48,6 +246,10 @@ impl std::fmt::Display for SplitLoaderError {
 /// the lazy loader with suspense. This means that the component will suspense until its body has
 /// been loaded.
 ///
+/// If the module fails to load, the component throws a `CapturedError` into the nearest
+/// `ErrorBoundary`. It wraps the [`SplitLoaderError`], which can be recovered with
+/// `CapturedError::downcast`, along with context naming the component that failed to load.
+///
 /// ```rust, ignore
 /// fn app() -> Element {
 ///     rsx! {
@@ -68,11 +270,36 @@ impl std::fmt::Display for SplitLoaderError {
 /// }
 /// ```
 pub struct LazyLoader<Args, Ret> {
-    imported: unsafe extern "C" fn(arg: Args) -> Ret,
+    imported: ImportedFn<Args, Ret>,
     key: &'static LocalKey<LazySplitLoader>,
 }
 
-impl<Args, Ret> LazyLoader<Args, Ret> {
+/// The address of a loader, which the options stored with their types, like fallbacks and seeds,
+/// are keyed by
+pub(crate) fn loader_key<Args, Ret>(loader: &'static LazyLoader<Args, Ret>) -> *const () {
+    loader as *const LazyLoader<Args, Ret> as *const ()
+}
+
+/// The function a loader calls, kept with the ABI it was defined with. Calling a Rust function
+/// through an `extern "C"` pointer is only sound if both ABIs happen to agree for `Args` and `Ret`,
+/// so functions that were never split out are called directly instead.
+enum ImportedFn<Args, Ret> {
+    /// The import of the function from its split module
+    Split(unsafe extern "C" fn(arg: Args) -> Ret),
+    /// The function itself, linked into the main module
+    Linked(fn(Args) -> Ret),
+}
+
+// Function pointers are always `Copy`, whatever their arguments are
+impl<Args, Ret> Clone for ImportedFn<Args, Ret> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Args, Ret> Copy for ImportedFn<Args, Ret> {}
+
+impl<Args: 'static, Ret: 'static> LazyLoader<Args, Ret> {
     /// Create a new lazy loader from a lazy imported function and a LazySplitLoader
     ///
     /// # Safety
@@ -83,90 +310,1244 @@ impl<Args, Ret> LazyLoader<Args, Ret> {
         imported: unsafe extern "C" fn(arg: Args) -> Ret,
         key: &'static LocalKey<LazySplitLoader>,
     ) -> Self {
-        Self { imported, key }
+        Self {
+            imported: ImportedFn::Split(imported),
+            key,
+        }
     }
 
     /// Create a new lazy loader that is already resolved.
     pub const fn preloaded(f: fn(Args) -> Ret) -> Self {
-        let imported =
-            unsafe { std::mem::transmute::<fn(Args) -> Ret, unsafe extern "C" fn(Args) -> Ret>(f) };
-
         thread_local! {
-            static LAZY: LazySplitLoader = LazySplitLoader::preloaded();
+            static LAZY: LazySplitLoader = LazySplitLoader::preloaded("preloaded");
         };
 
         Self {
-            imported,
+            imported: ImportedFn::Linked(f),
             key: &LAZY,
         }
     }
 
+    /// Create a new lazy loader for a split function that was linked into the main module instead
+    /// of being split out, with its own loader so the registry knows about it
+    ///
+    /// # Safety
+    /// The split loader behind `key` must belong to this loader alone, like the one `lazy_loader!`
+    /// declares next to it. Its policies and state are shared by every loader built with the same
+    /// key, so handing over the key of another split would mix up their loads.
+    #[doc(hidden)]
+    pub const unsafe fn inlined(
+        f: fn(Args) -> Ret,
+        key: &'static LocalKey<LazySplitLoader>,
+    ) -> Self {
+        Self {
+            imported: ImportedFn::Linked(f),
+            key,
+        }
+    }
+
     /// Load the lazy loader, returning an boolean indicating whether it loaded successfully
     pub async fn load(&'static self) -> bool {
-        *self.key.with(|inner| inner.lazy.clone()).as_ref().await
+        if LazySplitLoader::ensure_loaded(self.key).await {
+            return true;
+        }
+        match self.secondary() {
+            Some(secondary) => Box::pin(secondary.load()).await,
+            None => false,
+        }
+    }
+
+    /// Load the lazy loader like [`LazyLoader::load`], cancelling the download of its module when the
+    /// signal aborts.
+    ///
+    /// An aborted load resolves to `false` and leaves the loader failed with
+    /// [`SplitLoaderError::Aborted`], so it can be retried with [`LazyLoader::rearm`]. If the loader
+    /// was already started, the signal is ignored and this waits for the existing load. Custom
+    /// transports don't receive the signal.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn load_with_signal(&'static self, signal: web_sys::AbortSignal) -> bool {
+        self.key.with(|inner| {
+            if let SplitLoaderState::Deferred(_) = inner.loader.state.get() {
+                config::ensure_initialized();
+                inner
+                    .loader
+                    .start_with_option(false, "signal", signal.into());
+            }
+        });
+
+        self.load().await
+    }
+
+    /// Load the lazy loader like [`LazyLoader::load`], calling `on_progress` with the bytes of the
+    /// module received so far and its total size as it downloads.
+    ///
+    /// The total is `None` if the server didn't send a `Content-Length`. If the loader was already
+    /// started, or its function was never split out, `on_progress` is never called and this waits
+    /// for the existing load.
+    pub async fn load_with_progress(
+        &'static self,
+        on_progress: impl FnMut(u64, Option<u64>) + 'static,
+    ) -> bool {
+        #[cfg(target_arch = "wasm32")]
+        self.key.with(|inner| {
+            use wasm_bindgen::{closure::Closure, JsValue};
+
+            if let SplitLoaderState::Deferred(_) = inner.loader.state.get() {
+                let mut on_progress = on_progress;
+                // The glue owns the callback so it stays valid even if this future is dropped
+                let callback = Closure::<dyn FnMut(f64, JsValue)>::new(
+                    move |received: f64, total: JsValue| {
+                        on_progress(received as u64, total.as_f64().map(|total| total as u64))
+                    },
+                )
+                .into_js_value();
+
+                config::ensure_initialized();
+                inner.loader.start_with_option(false, "progress", callback);
+            }
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = on_progress;
+
+        self.load().await
+    }
+
+    /// Load the lazy loader like [`LazyLoader::load`], failing with [`SplitLoaderError::TimedOut`]
+    /// if it hasn't finished by `deadline`, so a parent operation can bound the total time of the
+    /// loads it depends on.
+    ///
+    /// `deadline` is a timestamp in milliseconds on the clock of `performance.now()`, like
+    /// `performance.now() + 2000.0` for two seconds from now, so the parent can compute it once and
+    /// hand the same deadline to every load it starts, each one getting whatever time is left. A
+    /// deadline that already passed fails right away unless the module has already loaded. Missing
+    /// the deadline only stops waiting: the load keeps going since other callers may share it, and
+    /// doesn't count as a failure of the loader. Outside the browser there is no timer to race
+    /// against, so the load is always awaited.
+    pub async fn load_with_deadline(&'static self, deadline: f64) -> Result<bool> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use futures_util::future::{select, Either};
+
+            if let Some(loaded) = self.peek() {
+                return Ok(loaded);
+            }
+            let remaining = deadline - glue::now();
+            if remaining > 0.0 {
+                let timeout = std::time::Duration::from_secs_f64(remaining / 1000.0);
+                if let Either::Left((loaded, _)) =
+                    select(Box::pin(self.load()), Box::pin(sleep(timeout))).await
+                {
+                    return Ok(loaded);
+                }
+            }
+
+            let name = self.key.with(|inner| inner.loader.name);
+            hooks::error(&SplitLoaderError::TimedOut, name);
+            Err(SplitLoaderError::TimedOut)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = deadline;
+            Ok(self.load().await)
+        }
+    }
+
+    /// Check whether this loader's module has loaded successfully, without starting a load
+    pub fn is_loaded(&'static self) -> bool {
+        self.key.with(|inner| inner.loader.is_loaded())
+    }
+
+    /// Check whether calling this loader right now would have to wait for its module, for code paths
+    /// that call the split synchronously when it's there and show a spinner otherwise:
+    ///
+    /// ```rust, ignore
+    /// if EDITOR.would_block() {
+    ///     show_spinner();
+    /// } else {
+    ///     EDITOR.call(args)?;
+    /// }
+    /// ```
+    ///
+    /// This is the inverse of [`LazyLoader::is_loaded`], named for the decision it supports. A
+    /// loader with a fallback still counts as blocking while its module isn't there, even though
+    /// [`LazyLoader::call`] would go to the fallback.
+    pub fn would_block(&'static self) -> bool {
+        !self.is_loaded()
+    }
+
+    /// Get the result of this loader's load if it has completed, without starting a load.
+    ///
+    /// Unlike [`LazyLoader::is_loaded`], this tells a failed load (`Some(false)`) apart from one
+    /// that hasn't completed yet (`None`).
+    pub fn peek(&'static self) -> Option<bool> {
+        match self.key.with(|inner| inner.loader.state.get()) {
+            SplitLoaderState::Completed(loaded) => Some(loaded),
+            SplitLoaderState::Deferred(_) | SplitLoaderState::Pending => None,
+        }
+    }
+
+    /// Check whether this loader's function lives in its own module that is downloaded on demand.
+    ///
+    /// This is `false` for loaders created with [`LazyLoader::preloaded`], like every loader in a
+    /// build without splitting, whose functions are linked into the main module. Asserting on this
+    /// catches a split that unexpectedly ended up inlined.
+    pub fn is_split(&'static self) -> bool {
+        self.key.with(|inner| inner.loader.load.is_some())
+    }
+
+    /// Get the thread-local split loader behind this loader, for building custom wrappers on top of
+    /// [`LazySplitLoader`], like awaiting it with [`LazySplitLoader::ensure_loaded`] directly.
+    ///
+    /// Each `lazy_loader!` declares its own split loader, so the key belongs to this loader alone
+    /// and loading through it loads this loader, while loaders made with [`LazyLoader::preloaded`]
+    /// all share one that is always loaded. The split loader holds the state and policies of the
+    /// load but knows nothing about this loader's signature, so calling the split function still
+    /// has to go through [`LazyLoader::call`], and fallbacks, secondaries and seeds stay on the
+    /// loader itself.
+    pub fn loader_key(&self) -> &'static LocalKey<LazySplitLoader> {
+        self.key
+    }
+
+    /// Replace the loaded module of this loader with a newer build of it fetched from
+    /// `new_chunk_url`, returning whether it was swapped. This is meant for hot reloading split code
+    /// during development, so it's only available in debug builds.
+    ///
+    /// The url is fetched like the module's own url: resolved against the chunk base url and its
+    /// content hash, rewritten by the url rewriter and fetched with the custom transport, or with
+    /// the loader's mirrors, timeout and priority.
+    ///
+    /// The new module is instantiated against the running main module like any other split module,
+    /// and instantiating it overwrites the slots of the function table that calls into the old one
+    /// go through. The switch happens at once, before any of the new module's code runs, so every
+    /// call after this resolves goes to the new functions while `self` stays the same loader. The new
+    /// module must have been built against the same main module, since it shares its memory layout.
+    ///
+    /// Wasm can't unload an instance, so the old module and anything it allocated leak. Loaders that
+    /// haven't loaded yet, or whose functions were never split out, aren't swapped.
+    #[cfg(debug_assertions)]
+    pub async fn hot_swap(&'static self, new_chunk_url: &str) -> bool {
+        if !self.is_split() || !self.is_loaded() {
+            return false;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            // The newer build is fetched with the loader's mirrors, timeout and priority
+            let swap = self.key.with(|inner| {
+                let mut swap = None;
+                glue::with_policy(&inner.loader.policy.borrow(), || {
+                    swap = glue::hot_swap(inner.loader.name, new_chunk_url)
+                });
+                swap
+            });
+            match swap {
+                Some(swap) => swap
+                    .await
+                    .ok()
+                    .and_then(|swapped| swapped.as_bool())
+                    .unwrap_or(false),
+                None => false,
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = new_chunk_url;
+            false
+        }
+    }
+
+    /// Run `on_loaded` once this loader completes, with whether its module loaded successfully.
+    ///
+    /// This doesn't start the load. If the loader already completed, `on_loaded` runs right away.
+    /// Each callback only runs for the first completion after it was registered.
+    pub fn on_loaded(&'static self, on_loaded: impl FnOnce(bool) + 'static) {
+        self.key.with(|inner| match inner.loader.state.get() {
+            SplitLoaderState::Completed(loaded) => on_loaded(loaded),
+            SplitLoaderState::Deferred(_) | SplitLoaderState::Pending => inner
+                .loader
+                .on_loaded
+                .borrow_mut()
+                .push(Box::new(on_loaded)),
+        })
+    }
+
+    /// Run `init` exactly once, right after this loader's module first loads successfully.
+    ///
+    /// The initializer runs before any task waiting on the load is woken, so [`LazyLoader::call`]
+    /// never succeeds before it has run. If the module already loaded, `init` runs right away.
+    /// Loaders that were never split out share their state, so for them `init` runs right away
+    /// every time this is called.
+    pub fn with_post_load(&'static self, init: fn()) {
+        self.key.with(|inner| {
+            let loader = &inner.loader;
+            if loader.load.is_none() {
+                return init();
+            }
+
+            loader.post_load.set(Some(init));
+            if loader.is_loaded() {
+                loader.run_post_load();
+            }
+        });
+    }
+
+    /// Mark this loader as permanently failed, like for a feature that was switched off at runtime.
+    ///
+    /// Any pending or future [`LazyLoader::load`] resolves to `false` and [`LazyLoader::call`]
+    /// returns [`SplitLoaderError::Disabled`] without touching the network, even if the module
+    /// already loaded. Loaders that were never split out are linked into the main module and can't
+    /// be marked as failed.
+    pub fn mark_failed(&'static self) {
+        self.key.with(|inner| {
+            if inner.loader.load.is_none() {
+                warn_log(format_args!(
+                    "{}: loaders that were never split out can't be marked as failed",
+                    inner.loader.name
+                ));
+                return;
+            }
+
+            inner.loader.error.replace(Some(SplitLoaderError::Disabled));
+            inner.loader.set_state(SplitLoaderState::Completed(false));
+            inner.loader.wake();
+        })
+    }
+
+    /// Turn a failed load back into a deferred one so that the next [`LazyLoader::load`] retries it,
+    /// like for a "tap to retry" button. Returns whether the loader was rearmed.
+    ///
+    /// This only affects loaders whose load failed - a loader that is still pending or that loaded
+    /// successfully is left alone and this returns `false`. Loaders that failed as often as the
+    /// threshold set with [`set_failure_threshold`] can't be rearmed either.
+    pub fn rearm(&'static self) -> bool {
+        self.key.with(|inner| inner.loader.rearm())
     }
 
     /// Call the lazy loader with the given arguments
     pub fn call(&'static self, args: Args) -> Result<Ret> {
-        let Some(true) = self.key.with(|inner| inner.lazy.try_get().copied()) else {
-            return Err(SplitLoaderError::FailedToLoad);
+        self.call_reporting(args, true)
+    }
+
+    /// Call the lazy loader, only panicking in strict mode and reporting the error to the error
+    /// hook if `report` is set
+    fn call_reporting(&'static self, args: Args, report: bool) -> Result<Ret> {
+        // The import of a mocked loader was never instantiated, so calling it would trap
+        let mocked = self.key.with(|inner| inner.loader.is_mocked());
+
+        if !self.is_loaded() || mocked {
+            if let (Some(false), Some(secondary)) = (self.peek(), self.secondary()) {
+                return secondary.call_reporting(args, report);
+            }
+
+            let waiting = self.key.with(|inner| {
+                matches!(
+                    inner.loader.state.get(),
+                    SplitLoaderState::Deferred(_) | SplitLoaderState::Pending
+                )
+            });
+            if let (true, Some(seed)) = (waiting, seed::get(self)) {
+                return Ok(seed);
+            }
+
+            if let Some(fallback) = policy::fallback(self) {
+                return Ok(fallback(args));
+            }
+
+            if mocked {
+                return Err(SplitLoaderError::Mocked);
+            }
+            let (error, name, started) = self.key.with(|inner| {
+                let started = !matches!(inner.loader.state.get(), SplitLoaderState::Deferred(_));
+                match consent::check(&inner.loader) {
+                    Some(error) => (error, inner.loader.name, true),
+                    None => (inner.loader.error(), inner.loader.name, started),
+                }
+            });
+            if report {
+                if !started && config::is_strict() {
+                    panic!("{name}: called the split function before its loader was ever loaded");
+                }
+                hooks::error(&error, name);
+            }
+            return Err(error);
+        }
+
+        let ret = match self.imported {
+            ImportedFn::Split(imported) => unsafe { imported(args) },
+            ImportedFn::Linked(f) => f(args),
         };
+        self.key.with(|inner| inner.loader.record_call());
+        Ok(ret)
+    }
+
+    /// Call the lazy loader like [`LazyLoader::call`], also returning how long the call took.
+    ///
+    /// Only the call itself is timed, not loading the module, which makes this useful for finding
+    /// split functions whose calls across the split boundary are measurably costly.
+    pub fn timed_call(&'static self, args: Args) -> Result<(Ret, std::time::Duration)> {
+        let start = glue::now();
+        let ret = self.call(args)?;
+        let elapsed = (glue::now() - start).max(0.0);
+        Ok((ret, std::time::Duration::from_secs_f64(elapsed / 1000.0)))
+    }
+
+    /// Get the number of tasks that are currently waiting for this loader's module to load.
+    ///
+    /// This is useful for diagnosing why many tasks are suspended on a single load.
+    pub fn waker_count(&'static self) -> usize {
+        self.key.with(|inner| inner.loader.wakers.borrow().len())
+    }
+
+    /// Get how many times the futures waiting for this loader's module were polled, counted across
+    /// every task that awaited the loader, like for spotting a busy loop behind a stalled suspense.
+    ///
+    /// A load that goes as expected polls each waiting task about twice: once when it starts waiting,
+    /// and once when the module arrives and wakes it. A count that keeps climbing while the loader is
+    /// still loading points at a task that is woken without the loader completing, like a waker that
+    /// wakes itself, while a task that was never polled again after the load completed points at a
+    /// lost wakeup. Polls of a loader that already completed count too. This is only available in
+    /// debug builds, so release builds don't pay for the counter.
+    #[cfg(debug_assertions)]
+    pub fn poll_count(&'static self) -> u64 {
+        self.key.with(|inner| inner.loader.polls.get())
+    }
+
+    /// Get how long each phase of loading this loader's module took.
+    ///
+    /// This is `None` until the module has been loaded, and for loaders that were never split out.
+    pub fn load_timing(&'static self) -> Option<LoadTiming> {
+        self.key.with(|inner| inner.loader.timing.get())
+    }
+
+    /// Get how long after this loader's module finished loading its split function was first called.
+    ///
+    /// Only calls that reach the split function count, not ones that went to a fallback. A module
+    /// that is called long after it arrived might as well be loaded later, while one that is called
+    /// right away might benefit from loading it earlier. This is `None` until the function has been
+    /// called, and for loaders that were never split out.
+    pub fn time_to_first_call(&'static self) -> Option<std::time::Duration> {
+        self.key.with(|inner| inner.loader.time_to_first_call.get())
+    }
+
+    /// Get roughly how many bytes the linear memory grew by while this loader's module was instantiated.
+    ///
+    /// Split modules share the main module's linear memory, so this is only an approximation: it
+    /// counts the growth while the module's data segments were written and its start function ran,
+    /// including any allocations other code made at the same time, and is `0` if the memory already
+    /// had enough room. Memory the split allocates once it is called isn't included. This is `None`
+    /// until the module has been loaded, and for loaders that were never split out.
+    pub fn memory_delta(&'static self) -> Option<u64> {
+        self.key.with(|inner| inner.loader.memory_delta.get())
+    }
+
+    /// Get whether this loader's module was served from the browser's HTTP cache rather than the
+    /// network, to see how well the chunks are cached in the wild.
+    ///
+    /// This is a heuristic based on the resource timing of the fetch: a module whose body arrived
+    /// without any bytes going over the wire counts as a cache hit. Servers on another origin have to
+    /// send a `Timing-Allow-Origin` header for the browser to report the sizes, and without it this is
+    /// `None`. Responses revalidated with the server transfer their headers, so they count as misses
+    /// even if the body came from the cache, and modules served by a service worker may count as
+    /// either depending on the browser. This is also `None` until the module has been loaded, for
+    /// modules loaded with a custom transport or from a response, and for loaders that were never
+    /// split out.
+    pub fn was_cache_hit(&'static self) -> Option<bool> {
+        self.key.with(|inner| inner.loader.cache_hit.get())
+    }
 
-        Ok(unsafe { (self.imported)(args) })
+    /// Get the names of the functions exported by this loader's module, like to check which
+    /// functions the bundler put into its chunk.
+    ///
+    /// This is `None` until the module has been loaded, and for loaders that were never split out.
+    pub fn chunk_exports(&'static self) -> Option<Vec<String>> {
+        self.key.with(|inner| inner.loader.exports.borrow().clone())
+    }
+
+    /// Get the bytes of the custom section with the given name in this loader's module, like
+    /// version or build info the build embedded in each chunk.
+    ///
+    /// The glue keeps the compiled module around once it loaded, so this is available as soon as
+    /// [`LazyLoader::load`] succeeded, and is `None` before that, if the load failed, if the module
+    /// was evicted by [`set_module_cache_limit`] or if it has no such section. If the module has
+    /// several sections with that name, the first one is returned. Loaders that were never split
+    /// out have no module of their own, so this is always `None` for them and outside the browser.
+    pub fn custom_section(&'static self, name: &str) -> Option<Vec<u8>> {
+        #[cfg(target_arch = "wasm32")]
+        return self.key.with(|inner| {
+            let section = glue::custom_section(inner.loader.module.borrow().as_ref()?, name);
+            module_cache::touch(&inner.loader);
+            section
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            _ = name;
+            None
+        }
+    }
+
+    /// Call the lazy loader if it has already loaded, otherwise return the default value of `Ret`.
+    ///
+    /// This is meant for optional enhancements that shouldn't break the page if their module isn't
+    /// available. Unlike [`LazyLoader::load`], this never starts a download, and a module that isn't
+    /// loaded is expected, so it neither panics in [strict mode](crate::strict_mode) nor reaches the
+    /// [error handler](crate::set_error_handler).
+    pub fn call_or_default(&'static self, args: Args) -> Ret
+    where
+        Ret: Default,
+    {
+        match self.call_reporting(args, false) {
+            Ok(ret) => ret,
+            Err(err) => {
+                let name = self.key.with(|inner| inner.loader.name);
+                debug_log(format_args!(
+                    "{name}: {err}, using the default value instead"
+                ));
+                Ret::default()
+            }
+        }
+    }
+}
+
+impl<Args: 'static, Ret: 'static> LazyLoader<Args, Ret> {
+    /// Queue a call to the split function until its module loads, starting the load if it hasn't
+    /// started yet.
+    ///
+    /// The call is queued right away, when this is called, rather than when the future is first
+    /// polled. Once the module loads, the queued calls are replayed in the order they were queued,
+    /// one after the other, before any task waiting on the loader is woken. Each future resolves
+    /// with the result of its call. If the load fails, every queued call goes through
+    /// [`LazyLoader::call`] all the same, so each one falls back to the loader's fallback or
+    /// resolves with the load's error. If the loader already completed, the call runs right away.
+    /// Dropping the future doesn't take the call out of the queue, only its result is discarded.
+    pub fn call_queued(&'static self, args: Args) -> impl Future<Output = Result<Ret>> {
+        let slot = Rc::new(RefCell::new((None, None::<Waker>)));
+        self.on_loaded({
+            let slot = slot.clone();
+            move |_| {
+                let (result, waker) = &mut *slot.borrow_mut();
+                *result = Some(self.call(args));
+                if let Some(waker) = waker.take() {
+                    waker.wake();
+                }
+            }
+        });
+        self.preload();
+
+        std::future::poll_fn(move |cx| {
+            let (result, waker) = &mut *slot.borrow_mut();
+            match result.take() {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    *waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl LazyLoader<(), ()> {
+    /// Load this loader's module and run its registration function, exactly once, returning
+    /// whether the module is registered.
+    ///
+    /// This is for splits that are only loaded for their side effects, like installing global
+    /// event handlers, rather than to call a function with arguments. By convention the module
+    /// declares its registration as a split function named `__register` that takes and returns
+    /// nothing, and this loader is the one for it:
+    ///
+    /// ```rust, ignore
+    /// static REGISTER_SHORTCUTS: wasm_split::LazyLoader<(), ()> =
+    ///     wasm_split::lazy_loader!(extern "shortcuts" fn __register(args: ()));
+    ///
+    /// fn __register(_: ()) {
+    ///     // Install the keyboard shortcuts
+    /// }
+    ///
+    /// REGISTER_SHORTCUTS.ensure_registered().await;
+    /// ```
+    ///
+    /// Awaiting this again, from any task, waits for the module without running `__register` a
+    /// second time. If the module fails to load, `__register` doesn't run and this returns `false`.
+    pub async fn ensure_registered(&'static self) -> bool {
+        if !self.load().await {
+            return false;
+        }
+        if self.key.with(|inner| inner.loader.registered.get()) {
+            return true;
+        }
+
+        let registered = self.call(()).is_ok();
+        self.key
+            .with(|inner| inner.loader.registered.set(registered));
+        registered
+    }
+}
+
+impl<Args, Fut: Future> LazyLoader<Args, Fut> {
+    /// Call a split function that returns a future, like an `async fn` split, and await its result,
+    /// failing with [`SplitLoaderError::CallTimedOut`] if it doesn't finish within `timeout`.
+    ///
+    /// This guards against a slow computation in the split hanging the UI. It only applies to
+    /// splits that return a future: a synchronous split runs to completion inside `call` before
+    /// there is anything to time out. The timeout only covers awaiting the future, not loading the
+    /// module, which has its own [`LoaderConfig::timeout`]. A future that times out is dropped.
+    /// Outside the browser there is no timer to race against, so the future is always awaited.
+    pub async fn call_with_timeout(
+        &'static self,
+        args: Args,
+        timeout: std::time::Duration,
+    ) -> Result<Fut::Output> {
+        let future = self.call(args)?;
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use futures_util::future::{select, Either};
+
+            match select(Box::pin(future), Box::pin(sleep(timeout))).await {
+                Either::Left((output, _)) => Ok(output),
+                Either::Right(_) => {
+                    let name = self.key.with(|inner| inner.loader.name);
+                    hooks::error(&SplitLoaderError::CallTimedOut, name);
+                    Err(SplitLoaderError::CallTimedOut)
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = timeout;
+            Ok(future.await)
+        }
     }
 }
 
-type Lazy = async_once_cell::Lazy<bool, SplitLoaderFuture>;
+/// A future that resolves once `timeout` has passed
+#[cfg(target_arch = "wasm32")]
+fn sleep(timeout: std::time::Duration) -> impl Future<Output = ()> {
+    let timer = Rc::new(RefCell::new((false, None::<Waker>)));
+    glue::set_timeout(timeout, {
+        let timer = timer.clone();
+        move || {
+            let (fired, waker) = &mut *timer.borrow_mut();
+            *fired = true;
+            if let Some(waker) = waker.take() {
+                waker.wake();
+            }
+        }
+    });
+    std::future::poll_fn(move |cx| {
+        let (fired, waker) = &mut *timer.borrow_mut();
+        if *fired {
+            return Poll::Ready(());
+        }
+        *waker = Some(cx.waker().clone());
+        Poll::Pending
+    })
+}
+
 type LoadCallbackFn = unsafe extern "C" fn(*const c_void, bool) -> ();
 type LoadFn = unsafe extern "C" fn(LoadCallbackFn, *const c_void) -> ();
+type OnLoadedFn = Box<dyn FnOnce(bool)>;
 
 pub struct LazySplitLoader {
-    lazy: Pin<Rc<Lazy>>,
+    loader: Rc<SplitLoader>,
 }
 
 impl LazySplitLoader {
     /// Create a new lazy split loader from a load function that is generated by the wasm-split macro
     ///
+    /// The name is the name of the split function and is only used for diagnostics.
+    ///
     /// # Safety
     ///
     /// This is unsafe because we're taking an arbitrary function pointer and using it as the loader.
     /// It is likely not instantiated when passed here, so it should never be called directly.
     #[doc(hidden)]
-    pub unsafe fn new(load: LoadFn) -> Self {
-        Self {
-            lazy: Rc::pin(Lazy::new({
-                SplitLoaderFuture {
-                    loader: Rc::new(SplitLoader {
-                        state: Cell::new(SplitLoaderState::Deferred(load)),
-                        waker: Cell::new(None),
-                    }),
-                }
-            })),
-        }
+    pub unsafe fn new(name: &'static str, load: LoadFn) -> Self {
+        let loader = Rc::new(SplitLoader::new(name, Some(load)));
+
+        #[cfg(feature = "registry")]
+        registry::register(&loader);
+
+        Self { loader }
     }
 
-    fn preloaded() -> Self {
+    /// Create a loader for a split function that was linked into the main module instead of being
+    /// split out, registering it so it shows up as inlined
+    ///
+    /// The name is the name of the split function and is only used for diagnostics.
+    #[doc(hidden)]
+    pub fn inlined(name: &'static str) -> Self {
+        let inlined = Self::preloaded(name);
+        #[cfg(feature = "registry")]
+        registry::register(&inlined.loader);
+        inlined
+    }
+
+    fn preloaded(name: &'static str) -> Self {
         Self {
-            lazy: Rc::pin(Lazy::new({
-                SplitLoaderFuture {
-                    loader: Rc::new(SplitLoader {
-                        state: Cell::new(SplitLoaderState::Completed(true)),
-                        waker: Cell::new(None),
-                    }),
-                }
-            })),
+            loader: Rc::new(SplitLoader::new(name, None)),
         }
     }
 
     /// Wait for the lazy loader to load
     pub async fn ensure_loaded(loader: &'static std::thread::LocalKey<LazySplitLoader>) -> bool {
-        *loader.with(|inner| inner.lazy.clone()).as_ref().await
+        loader.with(|inner| inner.loader.wait()).await
+    }
+
+    /// Load the module from a response that was already fetched elsewhere, like by a router
+    /// preloader, instead of fetching it again.
+    ///
+    /// If the loader was already started, the response is ignored and this waits for the existing
+    /// load. A response whose body was already consumed can't be compiled, so the module is fetched
+    /// as usual instead.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn instantiate_from_response(
+        loader: &'static std::thread::LocalKey<LazySplitLoader>,
+        response: web_sys::Response,
+    ) -> bool {
+        loader.with(|inner| {
+            if !matches!(inner.loader.state.get(), SplitLoaderState::Deferred(_)) {
+                return;
+            }
+
+            if response.body_used() {
+                warn_log(format_args!(
+                    "{}: the response body was already consumed, fetching the module instead",
+                    inner.loader.name
+                ));
+                return;
+            }
+
+            config::ensure_initialized();
+            inner
+                .loader
+                .start_with_option(false, "response", response.into());
+        });
+
+        Self::ensure_loaded(loader).await
     }
 }
 
 struct SplitLoader {
+    name: &'static str,
+    /// The function that starts loading the module, or `None` if this loader was never split out, in
+    /// which case all such loaders share it
+    load: Option<LoadFn>,
     state: Cell<SplitLoaderState>,
-    waker: Cell<Option<Waker>>,
+    /// The tasks waiting for this loader to complete
+    wakers: RefCell<Vec<Waker>>,
+    timing: Cell<Option<LoadTiming>>,
+    memory_delta: Cell<Option<u64>>,
+    /// The size of the module as it came over the wire, if known
+    size: Cell<Option<u64>>,
+    /// Whether the module was served from the HTTP cache, as far as the glue could tell
+    cache_hit: Cell<Option<bool>>,
+    exports: RefCell<Option<Vec<String>>>,
+    /// The name of the module in the glue, like `module_0_Editor`, once it has loaded
+    #[cfg(feature = "registry")]
+    chunk: RefCell<Option<String>>,
+    error: RefCell<Option<SplitLoaderError>>,
+    policy: RefCell<policy::LoaderPolicy>,
+    /// How many times the current load has been retried
+    attempts: Cell<u32>,
+    /// How many loads of this loader failed this session, after their retries
+    failures: Cell<u32>,
+    /// The idle callback handle of the preload scheduled with `preload_on_idle`, if any
+    idle_preload: Cell<Option<u32>>,
+    /// How many of the handles `preload_on_idle` returned for the scheduled preload haven't been
+    /// cancelled, so it's only cancelled once none of them want it anymore
+    idle_handles: Cell<u32>,
+    /// The initializer set with `with_post_load`, run once after the first successful load
+    post_load: Cell<Option<fn()>>,
+    post_load_ran: Cell<bool>,
+    /// Whether `ensure_registered` ran the module's `__register` function
+    registered: Cell<bool>,
+    /// The callbacks registered with `on_loaded`, run once this loader completes
+    on_loaded: RefCell<Vec<OnLoadedFn>>,
+    /// When the module finished loading, and how long after that the split function was first called
+    loaded_at: Cell<Option<f64>>,
+    time_to_first_call: Cell<Option<std::time::Duration>>,
+    /// How many times the split functions of this module were called
+    calls: Cell<u64>,
+    /// How many times the futures waiting for this loader were polled
+    #[cfg(debug_assertions)]
+    polls: Cell<u64>,
+    /// Whether the in-flight load was aborted by `reload_with` to be started again
+    restarting: Cell<bool>,
+    /// Whether the in-flight load was aborted by `on_route_change` to be started again once needed
+    cancelled: Cell<bool>,
+    /// The controller that aborts the in-flight fetch of this loader's module
+    #[cfg(target_arch = "wasm32")]
+    abort: RefCell<Option<web_sys::AbortController>>,
+    /// The options handed to the glue for only the pending load, like its signal, kept until
+    /// `call_load` hands them over since the load may wait for a slot or its dependencies first
+    #[cfg(target_arch = "wasm32")]
+    load_options: RefCell<Vec<(&'static str, wasm_bindgen::JsValue)>>,
+    /// The compiled module, kept around to read its custom sections
+    #[cfg(target_arch = "wasm32")]
+    module: RefCell<Option<js_sys::WebAssembly::Module>>,
+    /// Whether the compiled module was pinned with `pin_resident`, so it's never evicted
+    #[cfg(target_arch = "wasm32")]
+    pinned: Cell<bool>,
+}
+
+impl SplitLoader {
+    /// Create the loader of a split, deferred until its module is loaded with `load`, or already
+    /// loaded if it has no load function because it was never split out
+    fn new(name: &'static str, load: Option<LoadFn>) -> Self {
+        Self {
+            name,
+            load,
+            state: Cell::new(match load {
+                Some(load) => SplitLoaderState::Deferred(load),
+                None => SplitLoaderState::Completed(true),
+            }),
+            wakers: RefCell::new(Vec::new()),
+            timing: Cell::new(None),
+            memory_delta: Cell::new(None),
+            size: Cell::new(None),
+            cache_hit: Cell::new(None),
+            exports: RefCell::new(None),
+            #[cfg(feature = "registry")]
+            chunk: RefCell::new(None),
+            error: RefCell::new(None),
+            policy: RefCell::default(),
+            attempts: Cell::new(0),
+            failures: Cell::new(0),
+            idle_preload: Cell::new(None),
+            idle_handles: Cell::new(0),
+            post_load: Cell::new(None),
+            post_load_ran: Cell::new(false),
+            registered: Cell::new(false),
+            on_loaded: RefCell::new(Vec::new()),
+            loaded_at: Cell::new(None),
+            time_to_first_call: Cell::new(None),
+            calls: Cell::new(0),
+            #[cfg(debug_assertions)]
+            polls: Cell::new(0),
+            restarting: Cell::new(false),
+            cancelled: Cell::new(false),
+            #[cfg(target_arch = "wasm32")]
+            abort: RefCell::new(None),
+            #[cfg(target_arch = "wasm32")]
+            load_options: RefCell::new(Vec::new()),
+            #[cfg(target_arch = "wasm32")]
+            module: RefCell::new(None),
+            #[cfg(target_arch = "wasm32")]
+            pinned: Cell::new(false),
+        }
+    }
+
+    /// The error to report for a loader that isn't loaded
+    fn error(&self) -> SplitLoaderError {
+        self.error
+            .borrow()
+            .clone()
+            .unwrap_or(SplitLoaderError::FailedToLoad)
+    }
+
+    /// Kick off the download of this loader's module if it hasn't been started yet, queueing it
+    /// while loads are paused
+    fn start(self: &Rc<Self>) {
+        self.start_with(true)
+    }
+
+    /// Like `start`, handing the glue an option for only this load. Loads handed options meant
+    /// only for them, like a signal, ignore `pause_loads` unless `pausable` is set.
+    #[cfg(target_arch = "wasm32")]
+    fn start_with_option(
+        self: &Rc<Self>,
+        pausable: bool,
+        key: &'static str,
+        value: wasm_bindgen::JsValue,
+    ) {
+        if let SplitLoaderState::Deferred(_) = self.state.get() {
+            self.load_options.replace(vec![(key, value)]);
+            self.start_with(pausable);
+        }
+    }
+
+    fn start_with(self: &Rc<Self>, pausable: bool) {
+        let SplitLoaderState::Deferred(load) = self.state.get() else {
+            return;
+        };
+
+        // Mark the loader as pending before anything else runs, so loads requested while this one
+        // starts, like by another component mounting in the same frame, join it instead of calling
+        // the load function a second time
+        self.set_state(SplitLoaderState::Pending);
+        config::ensure_initialized();
+        events::emit(events::LoadEvent::Started { name: self.name });
+        waterfall::record_start(self.name);
+        debug_log(format_args!("{}: loading its module", self.name));
+
+        if let Some(error) = consent::check(self) {
+            return self.complete(
+                false,
+                glue::LoadReport {
+                    error: Some(error),
+                    ..Default::default()
+                },
+            );
+        }
+
+        #[cfg(feature = "testing")]
+        if let Some(error) = testing::injected_failure(self.name) {
+            return self.complete(
+                false,
+                glue::LoadReport {
+                    error: Some(error),
+                    ..Default::default()
+                },
+            );
+        }
+
+        if pausable {
+            let loader = self.clone();
+            return pause::start_or_queue(self.name, move || loader.invoke(load));
+        }
+        self.invoke(load);
+    }
+
+    /// Call the load function of this loader if it's still pending, which it might not be if it was
+    /// marked as failed while queued
+    fn invoke(self: &Rc<Self>, load: LoadFn) {
+        let SplitLoaderState::Pending = self.state.get() else {
+            return;
+        };
+        pause::started(Rc::as_ptr(self).cast());
+
+        // The dependencies are loaded first, and failing to load them fails this loader
+        #[cfg(target_arch = "wasm32")]
+        if dependencies::has_dependencies(self.name) {
+            let loader = self.clone();
+            config::spawn(Box::pin(async move {
+                match dependencies::load_dependencies(loader.name).await {
+                    Ok(()) => loader.call_load(load),
+                    Err(error) => loader.complete(
+                        false,
+                        glue::LoadReport {
+                            error: Some(error),
+                            ..Default::default()
+                        },
+                    ),
+                }
+            }));
+            return;
+        }
+
+        self.call_load(load);
+    }
+
+    /// Call the load function, handing the glue this loader's policies and inlined module
+    fn call_load(self: &Rc<Self>, load: LoadFn) {
+        // A dry run records the load instead, and completes it without calling the load function
+        #[cfg(feature = "testing")]
+        match testing::record_dry_run(self) {
+            Some(true) => return pause::finished(Rc::as_ptr(self).cast()),
+            Some(false) => {
+                return self.complete(
+                    false,
+                    glue::LoadReport {
+                        error: Some(SplitLoaderError::FailedToLoad),
+                        ..Default::default()
+                    },
+                )
+            }
+            None => {}
+        }
+
+        // The options handed to this load go last, so they win over the loader's policies
+        #[cfg(target_arch = "wasm32")]
+        let options = self.load_options.take();
+        let start = || {
+            glue::with_policy(&self.policy.borrow(), || {
+                let call = || unsafe {
+                    load(
+                        load_callback,
+                        Rc::<SplitLoader>::into_raw(self.clone()) as *const c_void,
+                    )
+                };
+
+                #[cfg(target_arch = "wasm32")]
+                glue::with_load_options(&options, call);
+
+                #[cfg(not(target_arch = "wasm32"))]
+                call();
+            })
+        };
+
+        // Every load can be aborted so that `reload_with` can restart it
+        #[cfg(target_arch = "wasm32")]
+        {
+            let abort = web_sys::AbortController::new().ok();
+            let signal = abort
+                .as_ref()
+                .map_or(wasm_bindgen::JsValue::UNDEFINED, |abort| {
+                    abort.signal().into()
+                });
+            self.abort.replace(abort);
+            glue::with_load_option("restartSignal", &signal, || {
+                schema::with_export_schema(self.name, || {
+                    inline::with_inline_chunk(self.name, start)
+                })
+            });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        start();
+    }
+
+    /// Abort the in-flight fetch of this loader's module so that `complete` starts it again,
+    /// returning whether there was a fetch to abort
+    fn restart(&self) -> bool {
+        self.abort_fetch(&self.restarting)
+    }
+
+    /// Abort the in-flight fetch of this loader's module so that `complete` moves it back to
+    /// deferred, returning whether there was a fetch to abort
+    #[cfg(feature = "registry")]
+    pub(crate) fn cancel(&self) -> bool {
+        self.abort_fetch(&self.cancelled)
+    }
+
+    /// Abort the in-flight fetch of this loader's module, setting `flag` for `complete` to tell why
+    fn abort_fetch(&self, flag: &Cell<bool>) -> bool {
+        #[cfg(target_arch = "wasm32")]
+        if let (SplitLoaderState::Pending, Some(abort)) =
+            (self.state.get(), self.abort.borrow().as_ref())
+        {
+            flag.set(true);
+            abort.abort();
+            return true;
+        }
+        _ = flag;
+        false
+    }
+
+    /// Wait for this loader to complete, starting the load if it hasn't been started yet
+    fn wait(self: &Rc<Self>) -> SplitLoaderFuture {
+        SplitLoaderFuture {
+            loader: self.clone(),
+        }
+    }
+
+    /// Move a failed loader back to deferred, returning whether it was failed and not latched by
+    /// `set_failure_threshold`
+    fn rearm(&self) -> bool {
+        if config::failure_threshold().is_some_and(|threshold| self.failures.get() >= threshold) {
+            return false;
+        }
+
+        match (self.state.get(), self.load) {
+            (SplitLoaderState::Completed(false), Some(load)) => {
+                self.error.replace(None);
+                self.set_state(SplitLoaderState::Deferred(load));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move this loader to `state`, keeping count of the loads in flight for `inflight_count`
+    fn set_state(&self, state: SplitLoaderState) {
+        let was_pending = matches!(self.state.replace(state), SplitLoaderState::Pending);
+        inflight::transition(was_pending, matches!(state, SplitLoaderState::Pending));
+
+        // The options of a load that ended before it was handed to the glue don't carry over
+        #[cfg(target_arch = "wasm32")]
+        if !matches!(state, SplitLoaderState::Pending) {
+            self.load_options.take();
+        }
+    }
+
+    /// Check whether this loader's module has loaded successfully
+    fn is_loaded(&self) -> bool {
+        matches!(self.state.get(), SplitLoaderState::Completed(true))
+    }
+
+    /// Check whether this loader was marked as loaded by `mock_all_loaded` without its module
+    fn is_mocked(&self) -> bool {
+        #[cfg(feature = "testing")]
+        return testing::is_mocked(self);
+
+        #[cfg(not(feature = "testing"))]
+        false
+    }
+
+    /// Record the outcome of loading this loader's module and wake the task waiting on it
+    fn complete(self: &Rc<Self>, success: bool, report: glue::LoadReport) {
+        pause::finished(Rc::as_ptr(self).cast());
+
+        // A loader that was marked as failed stays failed even if its module shows up afterwards
+        if let SplitLoaderState::Completed(_) = self.state.get() {
+            return self.wake();
+        }
+
+        // A load aborted by `reload_with` is started again with the new policies. Its waiters stay
+        // registered and are only woken once the new load completes.
+        #[cfg(target_arch = "wasm32")]
+        self.abort.replace(None);
+        if let (true, false, Some(load)) = (self.restarting.replace(false), success, self.load) {
+            debug_log(format_args!("{}: restarting the load", self.name));
+            self.attempts.set(0);
+            self.set_state(SplitLoaderState::Deferred(load));
+            return self.start();
+        }
+
+        // A load cancelled by `on_route_change` waits to be used again. Tasks still awaiting it
+        // need the module after all, so they start it again when woken.
+        if let (true, false, Some(load)) = (self.cancelled.replace(false), success, self.load) {
+            debug_log(format_args!("{}: cancelled the load", self.name));
+            self.attempts.set(0);
+            self.set_state(SplitLoaderState::Deferred(load));
+            return self.wake();
+        }
+
+        let retryable = report
+            .error
+            .as_ref()
+            .map_or(true, SplitLoaderError::is_retryable);
+        let retries = self.policy.borrow().retries;
+        if let (false, true, Some(load)) = (success, retryable, self.load) {
+            if self.attempts.get() < retries {
+                self.attempts.set(self.attempts.get() + 1);
+                debug_log(format_args!(
+                    "{}: failed to load, retrying ({}/{retries})",
+                    self.name,
+                    self.attempts.get()
+                ));
+                self.set_state(SplitLoaderState::Deferred(load));
+                return self.start();
+            }
+        }
+        self.attempts.set(0);
+
+        self.timing.set(report.timing);
+        if let (Some(timing), Some(threshold)) =
+            (report.timing, config::instantiate_warn_threshold())
+        {
+            if timing.instantiate > threshold {
+                warn_log(format_args!(
+                    "{}: instantiating the module blocked the main thread for {:?}",
+                    self.name, timing.instantiate
+                ));
+            }
+        }
+        self.memory_delta.set(report.memory_delta);
+        self.size.set(report.size);
+        self.cache_hit.set(report.cache_hit);
+        self.exports.replace(report.exports);
+        #[cfg(feature = "registry")]
+        self.chunk.replace(report.chunk);
+        #[cfg(target_arch = "wasm32")]
+        if let Some(module) = report.module {
+            self.module.replace(Some(module));
+            module_cache::touch(self);
+        }
+        recording::record(|| {
+            Some(recording::LoadRecord {
+                name: self.name,
+                size: report.size,
+                timing: report.timing?,
+            })
+        });
+        if let Some(SplitLoaderError::VersionMismatch) = report.error {
+            hooks::version_mismatch(self.name);
+        }
+        let counts_as_failure = !matches!(
+            report.error,
+            Some(SplitLoaderError::Aborted | SplitLoaderError::ConsentRequired)
+        );
+        if !success && counts_as_failure {
+            self.failures.set(self.failures.get() + 1);
+        }
+        self.error.replace(report.error);
+        if success {
+            self.loaded_at.set(Some(glue::now()));
+            self.run_post_load();
+        } else {
+            config::log(
+                LogLevel::Error,
+                format_args!("{}: failed to load: {}", self.name, self.error()),
+            );
+            hooks::error(&self.error(), self.name);
+            if !self.policy.borrow().fallback {
+                hooks::global_fallback(self.name);
+            }
+            if let Some(secondary) = self.policy.borrow().secondary {
+                secondary.with(|inner| inner.loader.start());
+            }
+        }
+        self.set_state(SplitLoaderState::Completed(success));
+        self.wake();
+        events::emit(events::LoadEvent::Completed {
+            name: self.name,
+            loaded: success,
+            cache_hit: self.cache_hit.get(),
+        });
+    }
+
+    /// Count a call of the split function, and record the time to the first call after its module
+    /// loaded
+    fn record_call(&self) {
+        self.calls.set(self.calls.get() + 1);
+        if let (Some(loaded_at), None) = (self.loaded_at.get(), self.time_to_first_call.get()) {
+            let elapsed = (glue::now() - loaded_at).max(0.0);
+            self.time_to_first_call
+                .set(Some(std::time::Duration::from_secs_f64(elapsed / 1000.0)));
+        }
+        waterfall::record_call(self);
+    }
+
+    /// Run the initializer set with `with_post_load` if it hasn't run yet
+    fn run_post_load(&self) {
+        if self.post_load_ran.get() {
+            return;
+        }
+        if let Some(init) = self.post_load.get() {
+            self.post_load_ran.set(true);
+            init();
+        }
+    }
+
+    /// Wake every task waiting on this loader, and run the callbacks registered with `on_loaded`
+    /// once it has completed
+    fn wake(&self) {
+        if let SplitLoaderState::Completed(loaded) = self.state.get() {
+            for on_loaded in self.on_loaded.take() {
+                on_loaded(loaded);
+            }
+        }
+
+        let scheduling = config::callback_scheduling();
+        for waker in self.wakers.take() {
+            match scheduling {
+                CallbackScheduling::Immediate => waker.wake(),
+                CallbackScheduling::Raf => glue::wake_on_next_frame(waker),
+            }
+        }
+    }
+
+    /// Register a task to be woken once this loader completes. Polling again from the same task
+    /// doesn't register it twice.
+    fn register_waker(&self, waker: &Waker) {
+        let mut wakers = self.wakers.borrow_mut();
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    /// Check whether a task has registered a waker that will be woken once this loader completes
+    #[cfg(feature = "registry")]
+    fn has_waker(&self) -> bool {
+        !self.wakers.borrow().is_empty()
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -184,31 +1565,215 @@ impl Future for SplitLoaderFuture {
     type Output = bool;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
-        unsafe extern "C" fn load_callback(loader: *const c_void, success: bool) {
-            let loader = unsafe { Rc::from_raw(loader as *const SplitLoader) };
-            loader.state.set(SplitLoaderState::Completed(success));
-            if let Some(waker) = loader.waker.take() {
-                waker.wake()
-            }
-        }
+        #[cfg(debug_assertions)]
+        self.loader.polls.set(self.loader.polls.get() + 1);
 
         match self.loader.state.get() {
-            SplitLoaderState::Deferred(load) => {
-                self.loader.state.set(SplitLoaderState::Pending);
-                self.loader.waker.set(Some(cx.waker().clone()));
-                unsafe {
-                    load(
-                        load_callback,
-                        Rc::<SplitLoader>::into_raw(self.loader.clone()) as *const c_void,
-                    )
-                };
+            SplitLoaderState::Deferred(_) => {
+                self.loader.register_waker(cx.waker());
+                self.loader.start();
                 Poll::Pending
             }
             SplitLoaderState::Pending => {
-                self.loader.waker.set(Some(cx.waker().clone()));
+                self.loader.register_waker(cx.waker());
                 Poll::Pending
             }
             SplitLoaderState::Completed(value) => Poll::Ready(value),
         }
     }
 }
+
+unsafe extern "C" fn load_callback(loader: *const c_void, success: bool) {
+    let loader = unsafe { Rc::from_raw(loader as *const SplitLoader) };
+    loader.complete(success, glue::take_report());
+}
+
+/// Log a debug diagnostic with the logger set with `set_logger`, or to the browser console in
+/// debug builds
+fn debug_log(message: std::fmt::Arguments<'_>) {
+    config::log(LogLevel::Debug, message);
+}
+
+/// Log a warning about misuse of the runtime with the logger set with `set_logger`, or to the
+/// browser console in debug builds
+fn warn_log(message: std::fmt::Arguments<'_>) {
+    config::log(LogLevel::Warn, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::Wake,
+    };
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A loader for the split function "test" with the given load function and state
+    fn test_loader(load: Option<LoadFn>, state: SplitLoaderState) -> Rc<SplitLoader> {
+        inflight::transition(false, matches!(state, SplitLoaderState::Pending));
+        let loader = SplitLoader::new("test", load);
+        loader.state.set(state);
+        Rc::new(loader)
+    }
+
+    #[test]
+    fn concurrent_awaiters_are_all_woken() {
+        let loader = test_loader(None, SplitLoaderState::Pending);
+
+        let counters = [0, 1].map(|_| Arc::new(CountingWaker(AtomicUsize::new(0))));
+        let wakers = counters.clone().map(Waker::from);
+        let mut futures = [0, 1].map(|_| SplitLoaderFuture {
+            loader: loader.clone(),
+        });
+
+        for (future, waker) in futures.iter_mut().zip(&wakers) {
+            let poll = Pin::new(future).poll(&mut Context::from_waker(waker));
+            assert_eq!(poll, Poll::Pending);
+        }
+        assert_eq!(loader.wakers.borrow().len(), 2);
+
+        loader.complete(true, glue::LoadReport::default());
+
+        for counter in &counters {
+            assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        }
+        for (future, waker) in futures.iter_mut().zip(&wakers) {
+            let poll = Pin::new(future).poll(&mut Context::from_waker(waker));
+            assert_eq!(poll, Poll::Ready(true));
+        }
+    }
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn counting_load(_callback: LoadCallbackFn, loader: *const c_void) {
+        LOADS.fetch_add(1, Ordering::SeqCst);
+        drop(unsafe { Rc::from_raw(loader as *const SplitLoader) });
+    }
+
+    #[test]
+    fn loads_requested_in_the_same_frame_are_coalesced() {
+        let loader = test_loader(
+            Some(counting_load),
+            SplitLoaderState::Deferred(counting_load),
+        );
+
+        let counters = [0, 1].map(|_| Arc::new(CountingWaker(AtomicUsize::new(0))));
+        let wakers = counters.clone().map(Waker::from);
+        let mut futures = [0, 1].map(|_| SplitLoaderFuture {
+            loader: loader.clone(),
+        });
+
+        // Both futures are polled before the load function had a chance to call back
+        for (future, waker) in futures.iter_mut().zip(&wakers) {
+            let poll = Pin::new(future).poll(&mut Context::from_waker(waker));
+            assert_eq!(poll, Poll::Pending);
+        }
+        assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+        assert!(matches!(loader.state.get(), SplitLoaderState::Pending));
+        assert_eq!(loader.wakers.borrow().len(), 2);
+
+        loader.complete(true, glue::LoadReport::default());
+        for (future, waker) in futures.iter_mut().zip(&wakers) {
+            let poll = Pin::new(future).poll(&mut Context::from_waker(waker));
+            assert_eq!(poll, Poll::Ready(true));
+        }
+        assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn queued_loads_start_by_priority_under_the_limit() {
+        thread_local! {
+            static STARTED: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+        }
+
+        unsafe extern "C" fn recording_load(_callback: LoadCallbackFn, loader: *const c_void) {
+            let loader = unsafe { Rc::from_raw(loader as *const SplitLoader) };
+            STARTED.with_borrow_mut(|started| started.push(loader.name));
+        }
+
+        pause::set_max_concurrent_loads(1);
+        pause::set_chunk_priority("high", 1);
+        let [first, low, high] = ["first", "low", "high"]
+            .map(|name| Rc::new(SplitLoader::new(name, Some(recording_load))));
+        for loader in [&first, &low, &high] {
+            loader.start();
+        }
+        assert_eq!(STARTED.take(), ["first"]);
+
+        first.complete(true, glue::LoadReport::default());
+        assert_eq!(STARTED.take(), ["high"]);
+
+        high.complete(true, glue::LoadReport::default());
+        assert_eq!(STARTED.take(), ["low"]);
+
+        low.complete(true, glue::LoadReport::default());
+        assert!(STARTED.take().is_empty());
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn timers_fire_on_the_test_clock() {
+        let time = Rc::new(Cell::new(0.0));
+        testing::set_clock({
+            let time = time.clone();
+            move || time.get()
+        });
+
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        for delay in [100, 50] {
+            let fired = fired.clone();
+            glue::set_timeout(std::time::Duration::from_millis(delay), move || {
+                fired.borrow_mut().push(delay)
+            });
+        }
+        assert_eq!(testing::run_due_timers(), 0);
+
+        time.set(60.0);
+        assert_eq!(testing::run_due_timers(), 1);
+        assert_eq!(*fired.borrow(), [50]);
+
+        time.set(100.0);
+        assert_eq!(testing::run_due_timers(), 1);
+        assert_eq!(*fired.borrow(), [50, 100]);
+        testing::reset_all();
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn dry_runs_record_loads_without_loading() {
+        unsafe extern "C" fn unreachable_load(_callback: LoadCallbackFn, _loader: *const c_void) {
+            unreachable!("a dry run called the load function");
+        }
+
+        testing::dry_run(true);
+        let loader = test_loader(
+            Some(unreachable_load),
+            SplitLoaderState::Deferred(unreachable_load),
+        );
+        loader.start();
+
+        assert_eq!(testing::dry_run_log().len(), 1);
+        assert_eq!(testing::dry_run_log()[0].name, "test");
+        assert!(loader.is_loaded() && loader.is_mocked());
+        testing::reset_all();
+        assert!(testing::dry_run_log().is_empty());
+    }
+    #[test]
+    fn disabled_and_untrusted_loads_are_not_retried() {
+        assert!(SplitLoaderError::FailedToLoad.is_retryable());
+        assert!(SplitLoaderError::TimedOut.is_retryable());
+        assert!(!SplitLoaderError::Untrusted.is_retryable());
+        assert!(!SplitLoaderError::Disabled.is_retryable());
+    }
+}
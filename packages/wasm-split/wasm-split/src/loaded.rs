@@ -0,0 +1,61 @@
+//! Calling split functions whose modules have loaded without checking the loader on every call.
+
+use crate::{ImportedFn, LazyLoader};
+use std::marker::PhantomData;
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Load this loader's module and return a handle that calls its split function directly, for
+    /// hot loops that call the split many times after it loaded.
+    ///
+    /// [`LazyLoader::call`] looks up the loader's thread-local state on every call to check that the
+    /// module has loaded, and [`LoadedLoader::call`] skips all of that. This is `None` if the module
+    /// failed to load, or if the loader was mocked as loaded since its module was never actually
+    /// instantiated. Calls through the handle bypass the loader entirely: they don't go to the
+    /// fallback or the seed, and don't count as calls of the split for
+    /// [`unused_loaded_chunks`](crate::unused_loaded_chunks) or the waterfall detection.
+    pub async fn into_loaded(&'static self) -> Option<LoadedLoader<Args, Ret>> {
+        if !self.load().await || self.key.with(|inner| inner.loader.is_mocked()) {
+            return None;
+        }
+        Some(LoadedLoader {
+            imported: self.imported,
+            _thread: PhantomData,
+        })
+    }
+}
+
+/// A loader whose module has loaded, returned by [`LazyLoader::into_loaded`].
+///
+/// # Safety invariant
+///
+/// The handle only exists once the module of its loader has been instantiated, and calls the
+/// import of the split function without checking again. That's sound because an instantiated
+/// module is never unloaded: its instance stays part of the running program and keeps the import
+/// valid for the rest of the program. Resetting the loader with `testing::reset_all` doesn't
+/// unload the module, and hot swapping it in debug builds replaces the functions the import calls
+/// instead of removing them. The handle can't leave the thread it was created on, since the module
+/// was only instantiated there.
+pub struct LoadedLoader<Args, Ret> {
+    imported: ImportedFn<Args, Ret>,
+    /// Keeps the handle on the thread whose instance of the module it calls into
+    _thread: PhantomData<*const ()>,
+}
+
+impl<Args, Ret> LoadedLoader<Args, Ret> {
+    /// Call the split function
+    pub fn call(&self, args: Args) -> Ret {
+        match self.imported {
+            // The module of the loader was instantiated before the handle was created
+            ImportedFn::Split(imported) => unsafe { imported(args) },
+            ImportedFn::Linked(f) => f(args),
+        }
+    }
+}
+
+impl<Args, Ret> Clone for LoadedLoader<Args, Ret> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Args, Ret> Copy for LoadedLoader<Args, Ret> {}
@@ -0,0 +1,153 @@
+//! Refusing to load modules that don't match a manifest of chunk hashes signed with Ed25519.
+
+use crate::{config, Result, SplitLoaderError};
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::collections::HashMap;
+
+/// The hash algorithms the browser checks the integrity of a fetch with
+const ALGORITHMS: [&str; 3] = ["sha256-", "sha384-", "sha512-"];
+
+/// Verify a manifest of the chunks this build may load against its Ed25519 signature, and then
+/// refuse to load any module that isn't in it or whose bytes don't match the hash it lists.
+///
+/// The manifest is text with a line per chunk, made of the name of the chunk and its hash in the
+/// [subresource integrity](https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity)
+/// format, separated by a space. Chunks are named like for [`set_chunk_hashes`](crate::set_chunk_hashes),
+/// like `module_0_Editor` or `chunk_0_app`. The last line is `signature` followed by the hex encoded
+/// Ed25519 signature of every byte before that line, newlines included, made with the private key of
+/// `pubkey`:
+///
+/// ```text
+/// module_0_Editor sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=
+/// chunk_0_app sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb
+/// signature 8f3a...
+/// ```
+///
+/// Returns [`SplitLoaderError::InvalidManifest`] with the reason when `pubkey` isn't a 32 byte
+/// Ed25519 public key, the signature line is missing or isn't 64 bytes of hex, the signature doesn't
+/// match the manifest, or a line isn't a chunk name and a `sha256-`, `sha384-` or `sha512-` hash.
+/// Nothing is changed then, so modules keep loading without any checks. Like the other global
+/// options, the manifest has to be set before the runtime is initialized, and setting it afterwards
+/// returns [`SplitLoaderError::InvalidManifest`] without checking it.
+///
+/// Once the manifest is set, loading a module that isn't in it fails with
+/// [`SplitLoaderError::Untrusted`] without fetching it. Modules fetched by the runtime are checked
+/// by the browser, which fails the fetch when the bytes don't match so the next mirror is tried and
+/// the load fails with [`SplitLoaderError::FailedToLoad`] once none of them match. Modules fetched
+/// with a custom [`ChunkTransport`](crate::ChunkTransport) or handed over as a response are hashed
+/// once they arrive instead, and fail with [`SplitLoaderError::Untrusted`] if they don't match.
+pub fn set_signed_manifest(manifest: &str, pubkey: &[u8]) -> Result<()> {
+    let invalid = |reason: &str| SplitLoaderError::InvalidManifest(reason.to_string());
+
+    if config::is_initialized() {
+        return Err(invalid("wasm-split was already initialized"));
+    }
+
+    let pubkey: &[u8; 32] = pubkey
+        .try_into()
+        .map_err(|_| invalid("the public key isn't 32 bytes long"))?;
+    let pubkey = VerifyingKey::from_bytes(pubkey)
+        .map_err(|_| invalid("the public key isn't an Ed25519 public key"))?;
+
+    let manifest = manifest.trim_end();
+    let (body, signature) = match manifest.rfind('\n') {
+        Some(end) => manifest.split_at(end + 1),
+        None => ("", manifest),
+    };
+    let signature = signature
+        .strip_prefix("signature ")
+        .ok_or_else(|| invalid("the last line isn't the signature"))?;
+    let signature = decode_hex(signature.trim())
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+        .ok_or_else(|| invalid("the signature isn't 64 bytes of hex"))?;
+    pubkey
+        .verify_strict(body.as_bytes(), &signature)
+        .map_err(|_| invalid("the signature doesn't match the manifest"))?;
+
+    let mut integrity = HashMap::new();
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        let hashed = |hash: &str| {
+            ALGORITHMS
+                .iter()
+                .any(|algorithm| hash.starts_with(algorithm))
+        };
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [chunk, hash] if hashed(hash) => integrity.insert(chunk.to_string(), hash.to_string()),
+            _ => return Err(invalid(&format!("`{line}` isn't a chunk and its hash"))),
+        };
+    }
+
+    config::set_chunk_integrity(integrity);
+    Ok(())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const KEY: [u8; 32] = [7; 32];
+
+    /// Sign the body with the fixed test key, appending the signature line
+    fn sign(body: &str) -> String {
+        let signature = SigningKey::from_bytes(&KEY).sign(body.as_bytes());
+        let hex: String = signature
+            .to_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        format!("{body}signature {hex}\n")
+    }
+
+    fn pubkey() -> [u8; 32] {
+        SigningKey::from_bytes(&KEY).verifying_key().to_bytes()
+    }
+
+    const BODY: &str = "module_0_Editor sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=\n";
+
+    #[test]
+    fn signed_manifests_are_accepted() {
+        set_signed_manifest(&sign(BODY), &pubkey()).unwrap();
+        assert!(crate::current_config().signed_manifest);
+    }
+
+    #[test]
+    fn tampered_manifests_are_rejected() {
+        let tampered = sign(BODY).replace("Editor", "Viewer");
+        let err = set_signed_manifest(&tampered, &pubkey()).unwrap_err();
+        assert!(
+            matches!(err, SplitLoaderError::InvalidManifest(reason) if reason.contains("doesn't match"))
+        );
+        assert!(!crate::current_config().signed_manifest);
+
+        let unhashed = sign("module_0_Editor 47DEQpj8HBSa\n");
+        assert!(set_signed_manifest(&unhashed, &pubkey()).is_err());
+        assert!(set_signed_manifest(BODY, &pubkey()).is_err());
+        assert!(set_signed_manifest(&sign(BODY), &[0; 31]).is_err());
+    }
+
+    #[test]
+    fn manifests_set_after_initializing_are_rejected() {
+        config::ensure_initialized();
+        assert!(set_signed_manifest(&sign(BODY), &pubkey()).is_err());
+        assert!(!crate::current_config().signed_manifest);
+    }
+
+    #[test]
+    fn hex_is_decoded() {
+        assert_eq!(decode_hex("00ff1A"), Some(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+}
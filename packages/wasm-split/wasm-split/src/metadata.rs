@@ -0,0 +1,54 @@
+//! Arbitrary metadata attached to loaders for tooling, like the feature area or team that owns a split.
+
+use crate::{warn_log, LazyLoader, SplitLoader};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+thread_local! {
+    static METADATA: RefCell<HashMap<*const SplitLoader, BTreeMap<String, String>>> = RefCell::new(HashMap::new());
+}
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Attach a key-value pair to this loader for tooling, replacing any value already set for the key.
+    ///
+    /// The metadata has no effect on loading. It is only surfaced by [`LazyLoader::metadata`] and in
+    /// the registry snapshot. Loaders that were never split out share their state, so metadata
+    /// can't be attached to them.
+    pub fn set_metadata(&'static self, key: &str, value: &str) {
+        self.key.with(|inner| {
+            if inner.loader.load.is_none() {
+                warn_log(format_args!(
+                    "{}: metadata can't be attached to loaders that were never split out",
+                    inner.loader.name
+                ));
+                return;
+            }
+
+            METADATA.with_borrow_mut(|metadata| {
+                metadata
+                    .entry(Rc::as_ptr(&inner.loader))
+                    .or_default()
+                    .insert(key.to_string(), value.to_string())
+            });
+        })
+    }
+
+    /// Get the metadata attached to this loader with [`LazyLoader::set_metadata`], sorted by key
+    pub fn metadata(&'static self) -> Vec<(String, String)> {
+        self.key
+            .with(|inner| get(&inner.loader).into_iter().collect())
+    }
+}
+
+/// Get the metadata attached to a loader
+pub(crate) fn get(loader: &SplitLoader) -> BTreeMap<String, String> {
+    METADATA.with_borrow(|metadata| {
+        metadata
+            .get(&(loader as *const SplitLoader))
+            .cloned()
+            .unwrap_or_default()
+    })
+}
@@ -0,0 +1,91 @@
+//! Bounding how many compiled modules the loaders keep around once they have loaded.
+
+use crate::LazyLoader;
+#[cfg(target_arch = "wasm32")]
+use crate::SplitLoader;
+#[cfg(target_arch = "wasm32")]
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+};
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static LIMIT: Cell<Option<usize>> = const { Cell::new(None) };
+    /// The loaders that keep a compiled module and weren't pinned, least recently used first
+    static RETAINED: RefCell<VecDeque<Rc<SplitLoader>>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Keep at most `limit` compiled modules around, evicting the least recently used ones beyond
+/// that to cap memory.
+///
+/// Once its module has loaded, a loader keeps the compiled `WebAssembly.Module` so that
+/// [`LazyLoader::custom_section`](crate::LazyLoader::custom_section) can read it. Loading a module
+/// or reading one of its sections counts as using it. Evicting a module only drops the compiled
+/// module: its split stays instantiated and its functions keep working, since wasm can't unload an
+/// instance. Its loader stays loaded, so the module is never compiled again and `custom_section`
+/// returns `None` for it from then on. Modules pinned with [`LazyLoader::pin_resident`] are never
+/// evicted and don't count toward the limit. A limit of `0` keeps no modules at all. There is no
+/// limit by default, so every module is kept. Lowering the limit evicts the modules beyond it
+/// right away. Outside the browser no modules are compiled, so there is nothing to evict.
+pub fn set_module_cache_limit(limit: usize) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        LIMIT.set(Some(limit));
+        evict();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = limit;
+}
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Keep this loader's compiled module for the rest of the program, no matter the limit set
+    /// with [`set_module_cache_limit`], like for a split whose custom sections are read often.
+    ///
+    /// A pinned module is never evicted and doesn't count toward the limit, so the limit only
+    /// bounds the modules that weren't pinned. A loader can be pinned before its module has loaded,
+    /// and the module is kept once it does. Pinning can't be undone.
+    pub fn pin_resident(&'static self) {
+        #[cfg(target_arch = "wasm32")]
+        self.key.with(|inner| {
+            inner.loader.pinned.set(true);
+            RETAINED.with_borrow_mut(|retained| {
+                retained.retain(|other| !Rc::ptr_eq(other, &inner.loader))
+            });
+        });
+    }
+}
+
+/// Mark the compiled module of the loader as the most recently used one, evicting the least
+/// recently used modules beyond the limit
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn touch(loader: &Rc<SplitLoader>) {
+    if loader.pinned.get() {
+        return;
+    }
+
+    RETAINED.with_borrow_mut(|retained| {
+        retained.retain(|other| !Rc::ptr_eq(other, loader));
+        retained.push_back(loader.clone());
+    });
+    evict();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn evict() {
+    let Some(limit) = LIMIT.get() else {
+        return;
+    };
+    loop {
+        let Some(loader) = RETAINED.with_borrow_mut(|retained| {
+            (retained.len() > limit)
+                .then(|| retained.pop_front())
+                .flatten()
+        }) else {
+            return;
+        };
+        loader.module.take();
+    }
+}
@@ -0,0 +1,64 @@
+//! Detecting slow connections so speculative preloads don't eat into a user's data.
+
+use crate::glue;
+use std::cell::Cell;
+
+thread_local! {
+    static PRELOAD_ON_SLOW_NETWORKS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The effective type of the user's connection, as reported by the browser's
+/// [Network Information API](https://developer.mozilla.org/en-US/docs/Web/API/Network_Information_API).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NetworkQuality {
+    /// `slow-2g`, like a congested mobile connection
+    Slow2G,
+    /// `2g`
+    TwoG,
+    /// `3g`
+    ThreeG,
+    /// `4g`, which is what most broadband and Wi-Fi connections report as well
+    FourG,
+    /// The browser doesn't support the Network Information API, or this isn't running in a browser
+    Unknown,
+}
+
+impl NetworkQuality {
+    /// Whether the connection is slow enough that speculative preloads are skipped
+    pub fn is_slow(self) -> bool {
+        matches!(self, NetworkQuality::Slow2G | NetworkQuality::TwoG)
+    }
+}
+
+/// Get the effective type of the user's connection, like to only preload on fast connections.
+///
+/// The browser estimates the effective type from the recent round-trip times and bandwidth, so it
+/// can change during a session. Browsers without the Network Information API, like Safari and
+/// Firefox, return [`NetworkQuality::Unknown`], which is treated like a fast connection.
+pub fn network_quality() -> NetworkQuality {
+    match glue::effective_connection_type().as_deref() {
+        Some("slow-2g") => NetworkQuality::Slow2G,
+        Some("2g") => NetworkQuality::TwoG,
+        Some("3g") => NetworkQuality::ThreeG,
+        Some("4g") => NetworkQuality::FourG,
+        _ => NetworkQuality::Unknown,
+    }
+}
+
+/// Keep preloading speculatively on slow connections.
+///
+/// By default, [`LazyLoader::preload_on_idle`](crate::LazyLoader::preload_on_idle) and
+/// [`predict_and_preload`](crate::predict_and_preload) skip preloading while
+/// [`network_quality`] reports a `2g` connection or slower, since those modules might never be
+/// needed. Modules that are actually used still load when they are asked for, and so do explicit
+/// [`LazyLoader::preload`](crate::LazyLoader::preload)s and the prefetches of navigations the user
+/// already started.
+pub fn set_preload_on_slow_networks(enabled: bool) {
+    PRELOAD_ON_SLOW_NETWORKS.set(enabled);
+}
+
+/// Check whether speculative preloads should be skipped on the current connection
+pub(crate) fn skip_speculative_preload() -> bool {
+    !PRELOAD_ON_SLOW_NETWORKS.get() && network_quality().is_slow()
+}
@@ -0,0 +1,56 @@
+//! Split functions that write their result into a `&mut` out-parameter.
+
+use crate::{LazyLoader, LoaderConfig, Result};
+
+/// A loader for a split function with a `&mut` out-parameter, returned by `lazy_loader!` for
+/// functions like `fn Measure(text: Text, out: &mut Metrics)`.
+///
+/// The out-parameter crosses the split as a raw pointer next to the argument, so the loader
+/// underneath takes `(Args, *mut Out)`. That loader is kept private and [`OutLoader::call_out`] is
+/// the only way to call it, so the pointer always comes from a `&mut Out` that is borrowed for the
+/// duration of the call.
+pub struct OutLoader<Args: 'static, Out: 'static> {
+    loader: LazyLoader<(Args, *mut Out), ()>,
+}
+
+impl<Args: 'static, Out: 'static> OutLoader<Args, Out> {
+    /// Wrap the loader that `lazy_loader!` generated for a split function with an out-parameter
+    ///
+    /// # Safety
+    /// The split function of `loader` must reborrow the pointer as `&mut Out` only for the duration
+    /// of the call, like the one `lazy_loader!` generates.
+    #[doc(hidden)]
+    pub const unsafe fn new(loader: LazyLoader<(Args, *mut Out), ()>) -> Self {
+        Self { loader }
+    }
+
+    /// Call the split function, if its module is loaded, with `out` to write its result into.
+    ///
+    /// The out-parameter is borrowed mutably for the duration of the call, so the split function has
+    /// exclusive access to it and can't hold on to it once the call returns.
+    pub fn call_out(&'static self, args: Args, out: &mut Out) -> Result<()> {
+        self.loader.call((args, out as *mut Out))
+    }
+
+    /// Load the loader's module like [`LazyLoader::load`]
+    pub async fn load(&'static self) -> bool {
+        self.loader.load().await
+    }
+
+    /// Check whether the loader's module has loaded like [`LazyLoader::is_loaded`]
+    pub fn is_loaded(&'static self) -> bool {
+        self.loader.is_loaded()
+    }
+
+    /// Check the state of the loader like [`LazyLoader::peek`]
+    pub fn peek(&'static self) -> Option<bool> {
+        self.loader.peek()
+    }
+
+    /// Configure how the loader's module is fetched like [`LazyLoader::configure`]. A fallback set
+    /// here is handed the out-parameter as the raw pointer, which stays valid for the duration of
+    /// the call.
+    pub fn configure(&'static self) -> LoaderConfig<'static, (Args, *mut Out), ()> {
+        self.loader.configure()
+    }
+}
@@ -0,0 +1,120 @@
+//! Pausing every split download for performance-critical moments, like a heavy animation, and
+//! limiting how many of them run at once.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+};
+
+/// A queued load with the name its priority is looked up by, and the function that starts it
+type Queued = (&'static str, Box<dyn FnOnce()>);
+
+thread_local! {
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+    static QUEUE: RefCell<VecDeque<Queued>> = const { RefCell::new(VecDeque::new()) };
+    static PRIORITIES: RefCell<HashMap<String, i32>> = RefCell::new(HashMap::new());
+    static MAX_CONCURRENT: Cell<Option<usize>> = const { Cell::new(None) };
+    /// The loads that were started and haven't completed yet, by the address of their loader or
+    /// blob
+    static RUNNING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Hold back every load that starts on this thread until [`resume_loads`] is called.
+///
+/// Loads that are already in flight keep going, and loads that start while paused are queued. The
+/// queued loaders stay pending, so anything awaiting them simply waits for longer. Loads handed a
+/// signal with [`LazyLoader::load_with_signal`](crate::LazyLoader::load_with_signal) or a response
+/// with [`LazySplitLoader::instantiate_from_response`](crate::LazySplitLoader::instantiate_from_response)
+/// aren't held back, and neither are [`load_critical`](crate::load_critical) and the prefetches
+/// that go straight to the glue. Loads of a [`LazyBlob`](crate::LazyBlob) are held back like the
+/// loads of split modules.
+pub fn pause_loads() {
+    PAUSED.set(true);
+}
+
+/// Start every load that was queued while paused with [`pause_loads`].
+///
+/// The queued loads start in order of the priorities set with [`set_chunk_priority`], highest
+/// first. Loads with the same priority start in the order they were requested, so among them the
+/// loader that was asked for first is downloaded first. With a limit set by
+/// [`set_max_concurrent_loads`], only as many start as the limit allows and the others keep
+/// waiting their turn.
+pub fn resume_loads() {
+    PAUSED.set(false);
+    start_queued();
+}
+
+/// Run at most `limit` loads at once on this thread, queueing the loads that start beyond that
+/// until one of the loads in flight completes.
+///
+/// Whenever a load completes, the queued load with the highest priority set with
+/// [`set_chunk_priority`] starts next, and loads with the same priority start in the order they
+/// were requested. The queued loaders stay pending like while paused, and the loads that aren't
+/// held back by [`pause_loads`] aren't held back by the limit either, though they count towards
+/// it. A limit of `0` is treated as `1` so loads still make progress. There is no limit by
+/// default. Raising the limit starts the queued loads it makes room for right away.
+pub fn set_max_concurrent_loads(limit: usize) {
+    MAX_CONCURRENT.set(Some(limit.max(1)));
+    start_queued();
+}
+
+/// Set the priority of the split function with the given name relative to the others, so it wins
+/// when several of them wait to be loaded at once, like the feature above the fold during boot.
+///
+/// When [`resume_loads`] starts the loads queued while paused, or a load completes and makes room
+/// under the limit set with [`set_max_concurrent_loads`], higher priorities start first and ties
+/// keep the order the loads were requested in. Splits default to a priority of `0`, so a negative
+/// priority moves a split behind the ones without a priority. Loads that aren't queued start right
+/// away regardless of their priority.
+pub fn set_chunk_priority(name: impl Into<String>, priority: i32) {
+    PRIORITIES.with_borrow_mut(|priorities| priorities.insert(name.into(), priority));
+}
+
+fn priority(name: &str) -> i32 {
+    PRIORITIES.with_borrow(|priorities| priorities.get(name).copied().unwrap_or(0))
+}
+
+/// Call `start` right away, or queue it to be called later if loads are paused or as many loads as
+/// the limit allows are in flight. `name` is the name its priority is looked up by.
+pub(crate) fn start_or_queue(name: &'static str, start: impl FnOnce() + 'static) {
+    if !PAUSED.get() && !at_limit() {
+        return start();
+    }
+
+    QUEUE.with_borrow_mut(|queue| queue.push_back((name, Box::new(start))));
+}
+
+/// Count the load of the loader or blob at `load` towards the limit until [`finished`] is called
+/// for it
+pub(crate) fn started(load: *const ()) {
+    RUNNING.with_borrow_mut(|running| running.push(load));
+}
+
+/// Stop counting the load of the loader or blob at `load` towards the limit, starting the queued
+/// loads that makes room for
+pub(crate) fn finished(load: *const ()) {
+    RUNNING.with_borrow_mut(|running| running.retain(|&other| other != load));
+    start_queued();
+}
+
+fn at_limit() -> bool {
+    MAX_CONCURRENT
+        .get()
+        .is_some_and(|limit| RUNNING.with_borrow(Vec::len) >= limit)
+}
+
+/// Start the queued loads with the highest priorities until paused or at the limit
+fn start_queued() {
+    while !PAUSED.get() && !at_limit() {
+        let next = QUEUE.with_borrow_mut(|queue| {
+            // The first of the loads with the highest priority, so ties start in request order
+            let next =
+                (0..queue.len()).max_by_key(|&i| (priority(queue[i].0), std::cmp::Reverse(i)))?;
+            queue.remove(next)
+        });
+        let Some((_, start)) = next else {
+            return;
+        };
+        start();
+    }
+}
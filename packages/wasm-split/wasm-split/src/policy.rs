@@ -0,0 +1,192 @@
+//! Per-loader policies for how a loader's module is fetched.
+
+use crate::{loader_key, LazyLoader, LazySplitLoader};
+use std::{any::Any, cell::RefCell, collections::HashMap, thread::LocalKey, time::Duration};
+
+thread_local! {
+    /// The `fn(Args) -> Ret` fallback of each loader, keyed by the address of the loader like seeds
+    /// so it's always looked up with the signature it was set with
+    static FALLBACKS: RefCell<HashMap<*const (), Box<dyn Any>>> = RefCell::new(HashMap::new());
+
+    /// The `&'static LazyLoader<Args, Ret>` secondary of each loader, keyed the same way
+    static SECONDARIES: RefCell<HashMap<*const (), Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// The priority hint passed to the browser's `fetch` for a loader's module.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadPriority {
+    /// Let the browser decide how to prioritize the module
+    #[default]
+    Auto,
+
+    /// Prioritize the module over other requests, like for a split that is needed right away
+    High,
+
+    /// Fetch the module after more important requests, like for a split that is only preloaded
+    Low,
+}
+
+/// The policies of a single loader, stored in its `SplitLoader`
+#[derive(Clone, Default)]
+pub(crate) struct LoaderPolicy {
+    pub(crate) retries: u32,
+    pub(crate) timeout: Option<Duration>,
+    /// Whether a loader of this split has a fallback, which is stored with its type in `FALLBACKS`
+    pub(crate) fallback: bool,
+    pub(crate) priority: LoadPriority,
+    pub(crate) mirrors: Vec<String>,
+    /// The split loader of the secondary set with `with_secondary`, to start it once this loader
+    /// fails. The secondary itself is stored with its type in `SECONDARIES`
+    pub(crate) secondary: Option<&'static LocalKey<LazySplitLoader>>,
+}
+
+/// A builder for the policies of a loader, returned by [`LazyLoader::configure`].
+///
+/// Each setter applies to the loader right away and affects every load that starts afterwards.
+///
+/// ```rust, ignore
+/// EDITOR
+///     .configure()
+///     .retries(2)
+///     .timeout(Duration::from_secs(10))
+///     .priority(LoadPriority::High)
+///     .mirrors(["https://cdn-backup.example.com"]);
+/// ```
+pub struct LoaderConfig<'a, Args, Ret> {
+    loader: &'a LazyLoader<Args, Ret>,
+}
+
+impl<Args: 'static, Ret: 'static> LazyLoader<Args, Ret> {
+    /// Configure how this loader's module is fetched.
+    ///
+    /// Loaders that were never split out are never fetched, so their configuration is ignored.
+    pub fn configure(&'static self) -> LoaderConfig<'static, Args, Ret> {
+        LoaderConfig { loader: self }
+    }
+
+    /// Abort the in-flight download of this loader's module and start it again with the policies
+    /// of `config`, returning whether there was a download to restart.
+    ///
+    /// ```rust, ignore
+    /// EDITOR.reload_with(EDITOR.configure().mirrors(["https://backup.example.com"]));
+    /// ```
+    ///
+    /// Tasks already awaiting the loader are handed over to the new load: the aborted load doesn't
+    /// complete the loader or wake them, so they resolve with the result of the load that replaced
+    /// it. Retries start over for the new load.
+    ///
+    /// The policies of `config` apply as soon as it's built, so a loader that hasn't started
+    /// downloading will use them anyway and nothing is restarted. Modules fetched with a custom
+    /// [`ChunkTransport`](crate::ChunkTransport) or handed over as a response can't be aborted and
+    /// finish their current load.
+    pub fn reload_with(&'static self, config: LoaderConfig<'static, Args, Ret>) -> bool {
+        debug_assert!(
+            std::ptr::eq(config.loader, self),
+            "reload_with was handed the config of another loader"
+        );
+        self.key.with(|inner| inner.loader.restart())
+    }
+
+    /// Fall back to the split of `secondary` whenever this loader's module fails to load, like to
+    /// roll out a new version of a chunk with the previous known-good one to fall back to.
+    ///
+    /// The two loaders keep their own state. This loader stays failed when its load fails, and the
+    /// secondary's load starts right away so it's likely there by the next call. From then on
+    /// [`LazyLoader::call`] goes to the secondary's [`LazyLoader::call`], with its own fallback and
+    /// errors, and [`LazyLoader::load`] waits for the secondary's load and resolves with its result.
+    /// Nothing goes to the secondary while this loader is still loading, only after it failed, and
+    /// once this loader is rearmed and loads, calls go back to its own split.
+    ///
+    /// ```rust, ignore
+    /// static EDITOR: LazyLoader<Args, Ret> = lazy_loader!(extern "editor" fn Editor(args: Args) -> Ret);
+    /// static EDITOR_STABLE: LazyLoader<Args, Ret> = lazy_loader!(extern "editor_stable" fn EditorStable(args: Args) -> Ret);
+    ///
+    /// EDITOR.with_secondary(&EDITOR_STABLE);
+    /// ```
+    ///
+    /// The secondary can have a secondary of its own, but the chain mustn't lead back to this
+    /// loader. Loaders that were never split out never fail, so they ignore the secondary.
+    pub fn with_secondary(&'static self, secondary: &'static LazyLoader<Args, Ret>) {
+        debug_assert!(
+            !std::ptr::eq(self, secondary),
+            "with_secondary was handed the loader itself"
+        );
+        self.configure().update(|policy| {
+            policy.secondary = Some(secondary.key);
+            SECONDARIES.with_borrow_mut(|secondaries| {
+                secondaries.insert(loader_key(self), Box::new(secondary))
+            });
+        });
+    }
+
+    /// The loader set with [`LazyLoader::with_secondary`], if any
+    pub(crate) fn secondary(&'static self) -> Option<&'static LazyLoader<Args, Ret>> {
+        SECONDARIES.with_borrow(|secondaries| {
+            secondaries
+                .get(&loader_key(self))?
+                .downcast_ref::<&'static LazyLoader<Args, Ret>>()
+                .copied()
+        })
+    }
+}
+
+impl<Args: 'static, Ret: 'static> LoaderConfig<'static, Args, Ret> {
+    fn update(self, f: impl FnOnce(&mut LoaderPolicy)) -> Self {
+        self.loader.key.with(|inner| {
+            // Loaders that were never split out all share the same `SplitLoader`
+            if inner.loader.load.is_some() {
+                f(&mut inner.loader.policy.borrow_mut())
+            }
+        });
+        self
+    }
+
+    /// Retry a failed load up to this many times before giving up. Loads that fail because the
+    /// module is from a different build or because they were aborted aren't retried.
+    pub fn retries(self, retries: u32) -> Self {
+        self.update(|policy| policy.retries = retries)
+    }
+
+    /// Fail the load with [`SplitLoaderError::TimedOut`](crate::SplitLoaderError::TimedOut) if
+    /// downloading the module takes longer than this.
+    /// This replaces the default set with
+    /// [`set_default_load_timeout`](crate::set_default_load_timeout) for this loader.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.update(|policy| policy.timeout = Some(timeout))
+    }
+
+    /// Call this function instead of the split function whenever the module isn't loaded, so
+    /// [`LazyLoader::call`] returns its result instead of an error.
+    pub fn fallback(self, fallback: fn(Args) -> Ret) -> Self {
+        let loader = self.loader;
+        self.update(|policy| {
+            policy.fallback = true;
+            FALLBACKS.with_borrow_mut(|fallbacks| {
+                fallbacks.insert(loader_key(loader), Box::new(fallback))
+            });
+        })
+    }
+
+    /// Set the priority hint of the module's request
+    pub fn priority(self, priority: LoadPriority) -> Self {
+        self.update(|policy| policy.priority = priority)
+    }
+
+    /// Base urls to try in order if fetching the module from the chunk base url fails.
+    pub fn mirrors(self, mirrors: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mirrors = mirrors.into_iter().map(Into::into).collect();
+        self.update(|policy| policy.mirrors = mirrors)
+    }
+}
+
+/// Get the fallback set on the loader with [`LoaderConfig::fallback`], if any
+pub(crate) fn fallback<Args: 'static, Ret: 'static>(
+    loader: &'static LazyLoader<Args, Ret>,
+) -> Option<fn(Args) -> Ret> {
+    FALLBACKS.with_borrow(|fallbacks| {
+        fallbacks
+            .get(&loader_key(loader))?
+            .downcast_ref::<fn(Args) -> Ret>()
+            .copied()
+    })
+}
@@ -0,0 +1,186 @@
+//! Preloading the splits of the routes the user is likely to visit next.
+
+use crate::glue;
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+thread_local! {
+    static ROUTE_CHUNKS: RefCell<HashMap<String, Vec<&'static str>>> = RefCell::new(HashMap::new());
+    /// The names of the splits of every route, leaked once each since there are only so many splits
+    static NAMES: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+/// Register the split functions whose modules a route needs, for [`predict_and_preload`].
+///
+/// The names are the names of the split functions, like the names passed to
+/// [`load_critical`](crate::load_critical). Registering a route again replaces its splits.
+///
+/// ```rust, ignore
+/// wasm_split::register_route_chunks("/editor", &["Toolbar", "Canvas"]);
+/// wasm_split::register_route_chunks("/settings", &["Settings"]);
+/// ```
+pub fn register_route_chunks(route: &str, chunks: &[&str]) {
+    let chunks = NAMES.with_borrow_mut(|names| {
+        chunks
+            .iter()
+            .map(|chunk| match names.get(*chunk) {
+                Some(name) => *name,
+                None => {
+                    let name = &*Box::leak(chunk.to_string().into_boxed_str());
+                    names.insert(name);
+                    name
+                }
+            })
+            .collect()
+    });
+    ROUTE_CHUNKS.with_borrow_mut(|routes| routes.insert(route.to_string(), chunks));
+}
+
+/// Get the names of the split functions registered for `route` with [`register_route_chunks`], in
+/// the order they were registered, like for showing which modules a page will download in dev
+/// tools or for picking what to preload.
+///
+/// This only reads the registration and doesn't load anything, nor does it check whether the
+/// modules are already loaded. A route that wasn't registered has no splits, so its list is empty.
+/// Routes are matched exactly, like by [`predict_and_preload`].
+pub fn chunks_for_route(route: &str) -> Vec<&'static str> {
+    ROUTE_CHUNKS.with_borrow(|routes| routes.get(route).cloned().unwrap_or_default())
+}
+
+/// Preload the modules of the routes the user is predicted to visit next at a low priority.
+///
+/// Routes that weren't registered with [`register_route_chunks`] are skipped, and so are modules
+/// that are already loaded or loading. Like [`load_critical`](crate::load_critical), this goes
+/// straight to the modules in the glue, so any loader used afterwards picks up the preloaded module.
+/// Nothing is preloaded on slow connections, unless enabled with
+/// [`set_preload_on_slow_networks`](crate::set_preload_on_slow_networks).
+pub fn predict_and_preload(routes: &[&str]) {
+    if crate::network::skip_speculative_preload() {
+        return;
+    }
+
+    let chunks = ROUTE_CHUNKS.with_borrow(|registered| {
+        routes
+            .iter()
+            .filter_map(|route| registered.get(*route))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+    });
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use crate::config;
+        use wasm_bindgen::JsValue;
+
+        config::ensure_initialized();
+        glue::with_load_option("priority", &JsValue::from_str("low"), || {
+            for chunk in &chunks {
+                if glue::load_modules(&[chunk]).is_err() {
+                    crate::warn_log(format_args!(
+                        "predict_and_preload: no split function named `{chunk}` exists"
+                    ));
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = chunks;
+}
+
+/// Tell the runtime the app navigated from `old_route` to `new_route`, so the loads the old route
+/// started make way for the modules the new one needs.
+///
+/// The splits of each route are the ones registered with [`register_route_chunks`]. The in-flight
+/// loads of the old route's splits that the new route doesn't need are aborted, and the loaders go
+/// back to waiting for their first use instead of failing, so a lazy component on the old route that
+/// is still rendered starts its load again. Then the modules of the new route start loading at a
+/// high priority, like with [`predict_and_preload`] but even on slow connections since they're
+/// needed right away. A module that is already downloading keeps the priority it started with.
+///
+/// Only loads started through a loader can be aborted: modules preloaded straight from the glue,
+/// like with [`predict_and_preload`], finish downloading. Routes that weren't registered have no
+/// splits.
+///
+/// ```rust, ignore
+/// wasm_split::on_route_change("/editor", "/settings");
+/// ```
+#[cfg(feature = "registry")]
+pub fn on_route_change(old_route: &str, new_route: &str) {
+    let (old_chunks, new_chunks) = ROUTE_CHUNKS.with_borrow(|registered| {
+        let chunks = |route| registered.get(route).cloned().unwrap_or_default();
+        (chunks(old_route), chunks(new_route))
+    });
+
+    for loader in crate::registry::all() {
+        let name = loader.name;
+        let stale = old_chunks.contains(&name) && !new_chunks.contains(&name);
+        if stale && loader.cancel() {
+            crate::debug_log(format_args!(
+                "on_route_change: cancelled the load of `{name}` for {new_route}"
+            ));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsValue;
+
+        crate::config::ensure_initialized();
+        glue::with_load_option("priority", &JsValue::from_str("high"), || {
+            for chunk in &new_chunks {
+                if glue::load_modules(&[chunk]).is_err() {
+                    crate::warn_log(format_args!(
+                        "on_route_change: no split function named `{chunk}` exists"
+                    ));
+                }
+            }
+        });
+    }
+}
+
+/// Preload the modules of the destination of every navigation as soon as it starts, using the
+/// browser's [Navigation API](https://developer.mozilla.org/en-US/docs/Web/API/Navigation_API).
+///
+/// `route_to_chunk` maps the path of the destination to the names of the split functions the route
+/// needs, like the names passed to [`load_critical`](crate::load_critical). Since the navigation
+/// starts the download before the router mounts the route, the module is usually on its way by the
+/// time a lazy component asks for it.
+///
+/// Returns whether the listener was installed. Browsers without the Navigation API, and native
+/// builds, return `false` and nothing is preloaded - lazy components still load their modules when
+/// they first render, so this is only ever an optimization. Call [`predict_and_preload`] from the
+/// router instead if navigations need to be prefetched everywhere.
+///
+/// ```rust, ignore
+/// wasm_split::install_navigation_prefetch(|path| match path {
+///     "/editor" => vec!["Toolbar", "Canvas"],
+///     _ => vec![],
+/// });
+/// ```
+pub fn install_navigation_prefetch(
+    route_to_chunk: impl Fn(&str) -> Vec<&'static str> + 'static,
+) -> bool {
+    glue::on_navigate(move |path| {
+        let chunks = route_to_chunk(&path);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            crate::config::ensure_initialized();
+            for chunk in chunks {
+                if glue::load_modules(&[chunk]).is_err() {
+                    crate::warn_log(format_args!(
+                        "install_navigation_prefetch: no split function named `{chunk}` exists"
+                    ));
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = chunks;
+    })
+}
@@ -0,0 +1,320 @@
+#[cfg(feature = "registry")]
+use crate::registry;
+use crate::{config, glue, network, LazyLoader, LazySplitLoader, Result, SplitLoaderState};
+use std::thread::LocalKey;
+
+impl<Args, Ret> LazyLoader<Args, Ret> {
+    /// Start downloading this loader's module without waiting for it to finish.
+    ///
+    /// Preloading doesn't need an executor - the download is driven by the browser and any task
+    /// that later awaits [`LazyLoader::load`] picks up the in-flight download.
+    pub fn preload(&'static self) {
+        self.key.with(|inner| inner.loader.start());
+    }
+
+    /// Preload this loader's module once the browser is idle.
+    ///
+    /// The returned handle can be used to cancel the preload if the module is no longer needed. If
+    /// the browser doesn't support `requestIdleCallback`, the module is preloaded immediately. If a
+    /// preload of this loader is already scheduled, it is reused instead of scheduling another one,
+    /// and it's only cancelled once every handle to it has been cancelled.
+    /// Nothing is preloaded on slow connections, unless enabled with
+    /// [`set_preload_on_slow_networks`](crate::set_preload_on_slow_networks).
+    pub fn preload_on_idle(&'static self) -> IdlePreload {
+        let key = self.key;
+        if network::skip_speculative_preload() {
+            return IdlePreload { key, handle: None };
+        }
+        let handle = key.with(|inner| {
+            let loader = &inner.loader;
+
+            // The download already started, so there is nothing left to schedule
+            if !matches!(loader.state.get(), SplitLoaderState::Deferred(_)) {
+                return None;
+            }
+
+            if let Some(handle) = loader.idle_preload.get() {
+                loader.idle_handles.set(loader.idle_handles.get() + 1);
+                return Some(handle);
+            }
+
+            let handle = glue::request_idle_callback(move || {
+                key.with(|inner| {
+                    inner.loader.idle_preload.set(None);
+                    inner.loader.start();
+                })
+            });
+            loader.idle_preload.set(handle);
+            loader.idle_handles.set(1);
+            handle
+        });
+
+        if handle.is_none() {
+            self.preload();
+        }
+
+        IdlePreload { key, handle }
+    }
+}
+
+/// Load the modules of the split functions with the given names in parallel, like the chunks an
+/// app needs before it can render anything.
+///
+/// Await this in `main` before mounting the app. Unlike [`ensure_loaded_by_name`](crate::ensure_loaded_by_name),
+/// this works for loaders that haven't been used yet since it goes straight to the modules in the
+/// glue, and any loader used afterwards picks up the loaded module. Every module is loaded even if
+/// some fail, and then the first failure is returned, leaving it up to the app whether to fail the
+/// boot or to continue and let the failed splits load again when they're used. An unknown name
+/// returns [`SplitLoaderError::UnknownLoader`](crate::SplitLoaderError::UnknownLoader) without
+/// loading anything. Outside the browser nothing is split out, so this always succeeds.
+pub async fn load_critical(names: &[&str]) -> Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use crate::SplitLoaderError;
+
+        config::ensure_initialized();
+        let loads = glue::load_modules(names)
+            .map_err(|name| SplitLoaderError::UnknownLoader(name.to_string()))?;
+
+        let mut result = Ok(());
+        for load in loads {
+            let loaded = load.await.ok().and_then(|loaded| loaded.as_bool());
+            if loaded != Some(true) && result.is_ok() {
+                result = Err(SplitLoaderError::FailedToLoad);
+            }
+        }
+        result
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        _ = names;
+        Ok(())
+    }
+}
+
+/// Load the chunks with the given names in parallel, for warming up many split functions at once
+/// without going through the loader of each one, returning whether each chunk loaded.
+///
+/// Chunks are named after the files the bundler writes without their `.wasm` extension: the module
+/// of a split function is `module_<index>_<function>` and a chunk shared by several modules is
+/// `chunk_<index>_<module>`, the same names as for [`set_chunk_hashes`](crate::set_chunk_hashes).
+/// The shared chunks a module needs are loaded along with it, and every chunk is only downloaded
+/// once no matter how many names or loaders refer to it.
+///
+/// `result[i]` is whether the chunk named `names[i]` loaded, so a failure is reported for its own
+/// chunk without failing the others. An unknown name is `false` and logs a warning. The loaders of
+/// the split functions in these chunks stay unloaded until they're used, and then complete right
+/// away without downloading anything. Outside the browser nothing is split out, so every chunk
+/// counts as loaded.
+pub async fn preload_chunks(names: &[&str]) -> Vec<bool> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        config::ensure_initialized();
+        // Every chunk starts loading right away, so they download in parallel
+        let loads = glue::load_chunks(names);
+        let mut loaded = Vec::with_capacity(names.len());
+        for (name, load) in names.iter().zip(loads) {
+            match load {
+                Some(load) => {
+                    let result = load.await.ok().and_then(|loaded| loaded.as_bool());
+                    loaded.push(result == Some(true));
+                }
+                None => {
+                    crate::warn_log(format_args!(
+                        "preload_chunks: no chunk named `{name}` exists"
+                    ));
+                    loaded.push(false);
+                }
+            }
+        }
+        loaded
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    vec![true; names.len()]
+}
+
+/// Load the chunks with the given names one after another, calling `on_step` with the index of
+/// each chunk and whether it loaded as soon as it finished, like for revealing the steps of an
+/// onboarding flow one at a time. Returns whether every chunk loaded.
+///
+/// Chunks are named like for [`preload_chunks`], but unlike it the next chunk only starts loading
+/// once the one before it has loaded. The sequence stops at the first chunk that fails to load:
+/// `on_step` is called with `false` for that chunk and isn't called for the ones after it, which
+/// aren't loaded at all, so a step is never revealed before the steps leading up to it. An unknown
+/// name fails its step like a chunk that didn't load and logs a warning. Outside the browser nothing
+/// is split out, so every step succeeds right away.
+pub async fn load_sequence(names: &[&str], mut on_step: impl FnMut(usize, bool)) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    config::ensure_initialized();
+
+    for (index, name) in names.iter().enumerate() {
+        #[cfg(target_arch = "wasm32")]
+        let loaded = match glue::load_chunks(&[name]).pop().flatten() {
+            Some(load) => load.await.ok().and_then(|loaded| loaded.as_bool()) == Some(true),
+            None => {
+                crate::warn_log(format_args!(
+                    "load_sequence: no chunk named `{name}` exists"
+                ));
+                false
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let loaded = {
+            _ = name;
+            true
+        };
+
+        on_step(index, loaded);
+        if !loaded {
+            return false;
+        }
+    }
+    true
+}
+
+/// Start every preload scheduled with [`LazyLoader::preload_on_idle`] right away at a high
+/// priority instead of waiting for the browser to go idle, like when the user is about to go
+/// offline and needs everything now.
+///
+/// The pending idle callbacks are cancelled, so the flushed preloads don't start a second time, and
+/// their [`IdlePreload`] handles no longer cancel anything. Only the request for the module is
+/// raised to a high priority, not the loader's own priority for later loads. While loads are paused
+/// with [`pause_loads`](crate::pause_loads), the flushed preloads join the queue of paused loads
+/// like any other load and still start at a high priority with
+/// [`resume_loads`](crate::resume_loads).
+#[cfg(feature = "registry")]
+pub fn flush_preloads() {
+    for loader in registry::all() {
+        let Some(handle) = loader.idle_preload.take() else {
+            continue;
+        };
+        glue::cancel_idle_callback(handle);
+
+        // Only this load is sped up, later loads keep the loader's own priority
+        #[cfg(target_arch = "wasm32")]
+        loader.start_with_option(true, "priority", "high".into());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        loader.start();
+    }
+}
+
+/// Preload the loader's module the first time `event` fires on `target`, like loading a widget's
+/// code on the first click or focus of an embed instead of on page load.
+///
+/// The listener is added with `once`, so the browser removes it as soon as the event fires for the
+/// first time and the preload only ever starts once, even for events like `pointermove` that fire
+/// over and over. The event isn't cancelled or stopped, so the page's own handlers still see it.
+/// If the module is already loaded or loading by the time the event fires, nothing happens. A loader
+/// that already started when this is called doesn't get a listener at all.
+///
+/// The returned handle removes the listener if the module isn't needed after all, like when the
+/// embed is taken off the page. Dropping the handle keeps the listener, which lives as long as the
+/// target until the event fires.
+///
+/// ```rust, ignore
+/// let button = document.get_element_by_id("chat-button").unwrap();
+/// wasm_split::load_on_event(&CHAT, &button, "pointerenter");
+/// ```
+#[cfg(target_arch = "wasm32")]
+pub fn load_on_event<Args, Ret>(
+    loader: &'static LazyLoader<Args, Ret>,
+    target: &web_sys::Element,
+    event: &str,
+) -> EventPreload {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let deferred = loader
+        .key
+        .with(|inner| matches!(inner.loader.state.get(), SplitLoaderState::Deferred(_)));
+    if !deferred {
+        return EventPreload { listener: None };
+    }
+
+    let listener = Closure::once_into_js(move || loader.preload());
+    let options = web_sys::AddEventListenerOptions::new();
+    options.set_once(true);
+    let added = target
+        .add_event_listener_with_callback_and_add_event_listener_options(
+            event,
+            listener.unchecked_ref(),
+            &options,
+        )
+        .is_ok();
+
+    EventPreload {
+        listener: added.then(|| (target.clone().into(), event.to_string(), listener)),
+    }
+}
+
+/// A handle to a preload scheduled with [`load_on_event`].
+///
+/// Dropping the handle does not remove the listener.
+#[cfg(target_arch = "wasm32")]
+pub struct EventPreload {
+    listener: Option<(web_sys::EventTarget, String, wasm_bindgen::JsValue)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl EventPreload {
+    /// Remove the listener if the event hasn't fired yet. Once it has fired, the preload already
+    /// started and runs to completion.
+    pub fn cancel(self) {
+        use wasm_bindgen::JsCast;
+
+        if let Some((target, event, listener)) = self.listener {
+            _ = target.remove_event_listener_with_callback(&event, listener.unchecked_ref());
+        }
+    }
+}
+
+/// A handle to a preload scheduled with [`LazyLoader::preload_on_idle`].
+///
+/// Dropping the handle does not cancel the preload.
+pub struct IdlePreload {
+    key: &'static LocalKey<LazySplitLoader>,
+    handle: Option<u32>,
+}
+
+impl IdlePreload {
+    /// Cancel the preload if it hasn't started yet and no other handle to it still wants it.
+    ///
+    /// Every call of [`LazyLoader::preload_on_idle`] that reused the scheduled preload returned a
+    /// handle to it, and the preload is only cancelled once all of them have been cancelled.
+    /// Cancellation is best-effort: once the browser has gone idle and the download has started, it
+    /// runs to completion. With [`set_preload_debounce`](crate::set_preload_debounce), the
+    /// cancellation only takes effect if the preload isn't requested again within the window.
+    pub fn cancel(self) {
+        let Some(handle) = self.handle else {
+            return;
+        };
+
+        let key = self.key;
+        key.with(|inner| {
+            let loader = &inner.loader;
+            if loader.idle_preload.get() == Some(handle) {
+                loader
+                    .idle_handles
+                    .set(loader.idle_handles.get().saturating_sub(1));
+            }
+        });
+        let cancel = move || {
+            key.with(|inner| {
+                let loader = &inner.loader;
+                if loader.idle_handles.get() == 0 && loader.idle_preload.get() == Some(handle) {
+                    glue::cancel_idle_callback(handle);
+                    loader.idle_preload.set(None);
+                }
+            })
+        };
+
+        let debounce = config::preload_debounce();
+        if debounce.is_zero() {
+            cancel()
+        } else {
+            glue::set_timeout(debounce, cancel)
+        }
+    }
+}
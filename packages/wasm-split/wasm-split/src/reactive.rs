@@ -0,0 +1,150 @@
+//! Exposing the state of loaders to the Dioxus reactive system, for building custom loading UI.
+
+use crate::{LazyLoader, SplitLoaderError};
+use dioxus_core::prelude::{spawn, RenderError, SuspendedFuture};
+use dioxus_hooks::{use_resource, Resource};
+use dioxus_signals::{Signal, Writable};
+
+/// The state of a loader, as tracked by [`use_load_state`].
+#[derive(Debug, Clone)]
+pub enum LoadState {
+    /// The loader hasn't completed yet, whether or not its load has started
+    Pending,
+    /// The loader's module loaded successfully
+    Loaded,
+    /// The loader's module failed to load
+    Failed(SplitLoaderError),
+}
+
+impl LoadState {
+    fn of<Args, Ret>(loader: &'static LazyLoader<Args, Ret>) -> Self {
+        match loader.peek() {
+            None => LoadState::Pending,
+            Some(true) => LoadState::Loaded,
+            Some(false) => LoadState::Failed(loader.key.with(|inner| inner.loader.error())),
+        }
+    }
+}
+
+/// Load a loader in a resource that resolves to the outcome of the load, for using Dioxus's usual
+/// loading and error handling for splits.
+///
+/// The resource awaits [`LazyLoader::load`], so unlike [`use_load_state`] this starts the load. It
+/// resolves to `Ok(())` once the module loaded and to the loader's error if it failed. Restarting
+/// the resource loads again, which only retries a failed load once the loader was rearmed with
+/// [`LazyLoader::rearm`].
+///
+/// ```rust, ignore
+/// let editor = wasm_split::use_loaded(&EDITOR);
+/// match &*editor.read() {
+///     None => rsx! { Spinner {} },
+///     Some(Ok(())) => rsx! { Editor {} },
+///     Some(Err(err)) => rsx! { "Failed to load the editor: {err}" },
+/// }
+/// ```
+pub fn use_loaded<Args: 'static, Ret: 'static>(
+    loader: &'static LazyLoader<Args, Ret>,
+) -> Resource<Result<(), SplitLoaderError>> {
+    use_resource(move || async move {
+        match loader.load().await {
+            true => Ok(()),
+            false => Err(loader.key.with(|inner| inner.loader.error())),
+        }
+    })
+}
+
+/// Suspend the component calling this until the loader's module has loaded, for splits that
+/// aren't the component's own chunk, like a split the component only needs in some states.
+///
+/// This is the suspense integration of `#[component(lazy)]` as a primitive that works with any
+/// loader. It returns `Ok(())` once the module has loaded, and otherwise starts the load and
+/// suspends the component on it, so the nearest suspense boundary shows its fallback until the
+/// component renders again with the loaded module. A load that failed is thrown into the nearest
+/// error boundary with the loader's error instead, until the loader is rearmed with
+/// [`LazyLoader::rearm`]. Since this isn't a hook, it can be called conditionally, and suspending
+/// on several loaders one after another loads them one after another.
+///
+/// ```rust, ignore
+/// if show_chart() {
+///     wasm_split::suspend_on(&CHART)?;
+///     return CHART.call(data);
+/// }
+/// ```
+pub fn suspend_on<Args, Ret>(loader: &'static LazyLoader<Args, Ret>) -> Result<(), RenderError> {
+    match loader.peek() {
+        Some(true) => Ok(()),
+        Some(false) => Err(loader.key.with(|inner| inner.loader.error()).into()),
+        None => {
+            let task = spawn(async move {
+                loader.load_suspended().await;
+            });
+            Err(RenderError::Suspended(SuspendedFuture::new(task)))
+        }
+    }
+}
+
+/// Track the state of a loader in a signal that updates once the loader completes.
+///
+/// This only observes the loader and doesn't start its load, so it's meant to sit next to whatever
+/// loads it, like a lazy component or [`LazyLoader::preload`]. Once the loader has completed, the
+/// signal no longer updates, even if the loader is rearmed.
+///
+/// ```rust, ignore
+/// let state = wasm_split::use_load_state(&EDITOR);
+/// match &*state.read() {
+///     LoadState::Pending => rsx! { Spinner {} },
+///     LoadState::Loaded => rsx! { Editor {} },
+///     LoadState::Failed(err) => rsx! { "Failed to load the editor: {err}" },
+/// }
+/// ```
+pub fn use_load_state<Args: 'static, Ret: 'static>(
+    loader: &'static LazyLoader<Args, Ret>,
+) -> Signal<LoadState> {
+    dioxus_core::use_hook(|| {
+        let initial = LoadState::of(loader);
+        let pending = matches!(initial, LoadState::Pending);
+        let mut state = Signal::new(initial);
+        if pending {
+            loader.on_loaded(move |_| {
+                // The component owning the signal might have been dropped in the meantime
+                if let Ok(mut state) = state.try_write() {
+                    *state = LoadState::of(loader);
+                }
+            });
+        }
+        state
+    })
+}
+
+/// Load a loader like [`LazyLoader::load_with_progress`], tracking the bytes of its module received
+/// so far and its total size in a signal, for reactive progress bars.
+///
+/// The total is `None` if the server didn't send a `Content-Length`. The signal is written for every
+/// progress report, which can be hundreds of times for a large module, so set an interval with
+/// [`set_progress_throttle`](crate::set_progress_throttle) to avoid re-rendering on each of them.
+/// The last progress is always reported, so a finished bar never lags behind. If the loader was already started, or its function was never split out, the signal stays at
+/// `(0, None)`.
+///
+/// ```rust, ignore
+/// let progress = wasm_split::use_load_progress(&EDITOR);
+/// let (received, total) = progress();
+/// rsx! { progress { value: received as f64, max: total.map(|total| total as f64) } }
+/// ```
+pub fn use_load_progress<Args: 'static, Ret: 'static>(
+    loader: &'static LazyLoader<Args, Ret>,
+) -> Signal<(u64, Option<u64>)> {
+    dioxus_core::use_hook(|| {
+        let mut progress = Signal::new((0, None));
+        let on_progress = move |received, total| {
+            // The component owning the signal might have been dropped in the meantime
+            if let Ok(mut progress) = progress.try_write() {
+                *progress = (received, total);
+            }
+        };
+
+        spawn(async move {
+            loader.load_with_progress(on_progress).await;
+        });
+        progress
+    })
+}
@@ -0,0 +1,43 @@
+//! Opt-in recording of every load for comparing load performance across builds.
+
+use crate::LoadTiming;
+use std::cell::RefCell;
+
+thread_local! {
+    static RECORDING: RefCell<Option<Vec<LoadRecord>>> = const { RefCell::new(None) };
+}
+
+/// A record of a split module that was loaded while recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadRecord {
+    /// The name of the split function whose module was loaded
+    pub name: &'static str,
+
+    /// The size of the module in bytes as it came over the wire, if known. This is the
+    /// `Content-Length` of the response, so it is the compressed size if the server compressed it.
+    pub size: Option<u64>,
+
+    /// How long each phase of the load took
+    pub timing: LoadTiming,
+}
+
+/// Start recording every module that loads on this thread, discarding anything recorded before.
+///
+/// Nothing is recorded unless recording was started, so there is no overhead when it's unused.
+pub fn start_recording() {
+    RECORDING.set(Some(Vec::new()));
+}
+
+/// Stop recording, returning the records of every module that loaded since [`start_recording`] in
+/// the order they finished loading.
+pub fn stop_recording() -> Vec<LoadRecord> {
+    RECORDING.take().unwrap_or_default()
+}
+
+pub(crate) fn record(record: impl FnOnce() -> Option<LoadRecord>) {
+    RECORDING.with_borrow_mut(|recording| {
+        if let (Some(recording), Some(record)) = (recording.as_mut(), record()) {
+            recording.push(record);
+        }
+    })
+}
@@ -0,0 +1,494 @@
+//! A thread-local registry of every split loader that has been initialized.
+//!
+//! Loaders are registered lazily - the thread-local holding a [`LazySplitLoader`](crate::LazySplitLoader)
+//! is only initialized once it is first used, so loaders that have never been touched won't show up here.
+//!
+//! The registry and everything that reads it are behind the `registry` feature, which is on by
+//! default. Builds without it don't keep a list of their loaders at all: the functions that look up
+//! loaders by name, inspect their state or act on all of them at once like
+//! [`flush_preloads`](crate::flush_preloads) and [`on_route_change`](crate::on_route_change) are
+//! compiled out, while loading and calling a [`LazyLoader`](crate::LazyLoader) works the same.
+//! Per-loader timings and the other observations on a loader itself, like
+//! [`LazyLoader::load_timing`](crate::LazyLoader::load_timing), don't need the registry and stay available.
+
+use crate::{Result, SplitLoader, SplitLoaderError, SplitLoaderState};
+use std::{cell::RefCell, rc::Rc};
+
+thread_local! {
+    static REGISTRY: RefCell<Vec<Rc<SplitLoader>>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn register(loader: &Rc<SplitLoader>) {
+    REGISTRY.with(|registry| registry.borrow_mut().push(loader.clone()));
+}
+
+/// Get every loader registered on this thread
+pub(crate) fn all() -> Vec<Rc<SplitLoader>> {
+    REGISTRY.with(|registry| registry.borrow().clone())
+}
+
+/// Wait for the loader of the split function with the given name to load, returning whether it
+/// loaded successfully.
+///
+/// Since loaders are registered lazily, this only finds loaders that have already been used on this
+/// thread. Any other name returns [`SplitLoaderError::UnknownLoader`].
+pub async fn ensure_loaded_by_name(name: &str) -> Result<bool> {
+    let loader = REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .find(|loader| loader.name == name)
+            .cloned()
+    });
+
+    match loader {
+        Some(loader) => Ok(loader.wait().await),
+        None => Err(SplitLoaderError::UnknownLoader(name.to_string())),
+    }
+}
+
+/// Get the names of every split function in the build without loading anything, like for a smoke
+/// test that loads each of them against a live server to check that every chunk is deployed.
+///
+/// The loaders registered on this thread come first, in the order they were registered, followed
+/// by the split functions the glue knows about whose loaders haven't been used yet. Load the
+/// modules of the latter with [`load_critical`](crate::load_critical), which doesn't need their
+/// loaders. Outside the browser there is no glue, so only the registered loaders are listed.
+pub fn all_loader_names() -> Vec<&'static str> {
+    let registered = REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .map(|loader| loader.name)
+            .collect::<Vec<_>>()
+    });
+    #[cfg(target_arch = "wasm32")]
+    let registered = registered.into_iter().chain(crate::glue::module_names());
+
+    let mut names = Vec::new();
+    for name in registered {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Get the names of all loaders that are currently pending and have a task waiting on them.
+///
+/// This is useful for debugging suspense boundaries that never resolve - if a loader shows up here
+/// for a long time, its module most likely failed to download without reporting back.
+#[cfg(debug_assertions)]
+pub fn pending_loaders() -> Vec<&'static str> {
+    use crate::SplitLoaderState;
+
+    REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .filter(|loader| matches!(loader.state.get(), SplitLoaderState::Pending))
+            .filter(|loader| loader.has_waker())
+            .map(|loader| loader.name)
+            .collect()
+    })
+}
+
+/// Check whether any task is still waiting on a loader that hasn't completed.
+///
+/// Test runners can poll this to decide when the split system has settled, e.g. before taking a
+/// snapshot of the DOM, without awaiting every loader. Loaders that are pending without anything
+/// waiting on them, like preloads, don't count.
+pub fn has_pending_work() -> bool {
+    use crate::SplitLoaderState;
+
+    REGISTRY.with(|registry| {
+        registry.borrow().iter().any(|loader| {
+            matches!(loader.state.get(), SplitLoaderState::Pending) && loader.has_waker()
+        })
+    })
+}
+
+/// Count the distinct split modules that have loaded on this thread.
+///
+/// Several loaders can share a module, so this counts the modules rather than the loaders, telling
+/// them apart by the name the bundler registered the module with in the glue, like
+/// `module_0_Editor`. Loaders of a glue that doesn't name its modules are told apart by the
+/// function that downloads them instead. Shared chunks that are only loaded as dependencies of
+/// other modules aren't visible to the loaders and aren't counted.
+pub fn loaded_chunk_count() -> usize {
+    REGISTRY.with(|registry| {
+        let registry = registry.borrow();
+        let mut chunks = registry
+            .iter()
+            // Mocked loaders never fetched their module
+            .filter(|loader| loader.is_loaded() && !loader.is_mocked())
+            .filter_map(|loader| {
+                let load = loader.load?;
+                Some(match loader.chunk.borrow().clone() {
+                    Some(chunk) => Ok(chunk),
+                    None => Err(load as usize),
+                })
+            })
+            .collect::<Vec<_>>();
+        chunks.sort_unstable();
+        chunks.dedup();
+        chunks.len()
+    })
+}
+
+/// Sum up the sizes of the split modules that haven't loaded yet, to measure how much code
+/// splitting keeps out of the initial bundle.
+///
+/// The sizes come from [`set_chunk_sizes`](crate::set_chunk_sizes), since a module's size is only
+/// known once it has been fetched otherwise, and this is `0` until they're set. Shared chunks count
+/// like the modules of split functions, and a module stops counting as deferred as soon as it has
+/// been instantiated, whether by its loader, as a dependency of another module or through
+/// [`preload_chunks`](crate::preload_chunks). Names the glue doesn't know, like ones from the
+/// manifest of another build, aren't counted. Outside the browser nothing is split out, so nothing
+/// is deferred.
+pub fn deferred_bytes() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    return crate::config::chunk_sizes()
+        .unwrap_or_default()
+        .iter()
+        .filter(|(name, _)| crate::glue::chunk_loaded(name) == Some(false))
+        .map(|(_, size)| size)
+        .sum();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    0
+}
+
+/// Get the names of the loaders whose module loaded but whose split functions were never called,
+/// for finding preloads that waste bandwidth on modules the session never needed.
+///
+/// Calls that went to a fallback don't count, and neither do loaders that were never split out or
+/// that were mocked as loaded. Loaders in the same module share their calls, so a module counts as
+/// used once any of its split functions was called. This is purely diagnostic, and a module that
+/// hasn't been called yet may still be called later in the session.
+pub fn unused_loaded_chunks() -> Vec<&'static str> {
+    REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .filter(|loader| loader.load.is_some() && !loader.is_mocked())
+            .filter(|loader| loader.is_loaded() && loader.calls.get() == 0)
+            .map(|loader| loader.name)
+            .collect()
+    })
+}
+
+/// Check that every loader used on this thread is backed by a module of its own, returning the
+/// names of the loaders whose split functions were linked into the main module instead.
+///
+/// Run this at boot in staging to catch a build misconfiguration that silently disabled splitting.
+/// Splits that were inlined on purpose because their feature is disabled are listed as well.
+/// Loaders are registered lazily, so only loaders that have been used on this thread are checked,
+/// and a build without any splitting only fails once one of them was used. Outside the browser nothing is split out, so this fails
+/// as soon as any loader was used.
+pub fn assert_all_split() -> std::result::Result<(), Vec<&'static str>> {
+    let inlined = REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .filter(|loader| loader.load.is_none())
+            .map(|loader| loader.name)
+            .collect::<Vec<_>>()
+    });
+
+    match inlined.is_empty() {
+        true => Ok(()),
+        false => Err(inlined),
+    }
+}
+
+/// A loader in the snapshot of [`registry_snapshot_json`], which can be deserialized again to
+/// compare snapshots with [`diff_snapshots`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LoaderSnapshot {
+    /// The name of the split function
+    pub name: String,
+    /// Whether the loader's function lives in its own module
+    pub split: bool,
+    /// One of `"deferred"`, `"pending"`, `"loaded"` or `"failed"`
+    pub state: String,
+    /// The size of the module in bytes as it came over the wire
+    pub size: Option<u64>,
+    /// The timing of the module's load
+    pub timing: Option<TimingSnapshot>,
+    /// The metadata attached with [`LazyLoader::set_metadata`](crate::LazyLoader::set_metadata)
+    pub metadata: std::collections::BTreeMap<String, String>,
+}
+
+/// The timing of a load in milliseconds
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TimingSnapshot {
+    pub fetch: f64,
+    pub compile: f64,
+    pub instantiate: f64,
+}
+
+#[cfg(feature = "serde")]
+impl TimingSnapshot {
+    /// The time the whole load took in milliseconds
+    pub fn total(&self) -> f64 {
+        self.fetch + self.compile + self.instantiate
+    }
+}
+
+/// Serialize the state of every loader registered on this thread to JSON, for dev tools or test
+/// harnesses that inspect the loaders from the outside.
+///
+/// The JSON is an array with an object for every loader in the order they were registered. `state`
+/// is one of `"deferred"`, `"pending"`, `"loaded"` or `"failed"`, `size` is the size of the module
+/// in bytes as it came over the wire, and the phases of `timing` are in milliseconds. The size and
+/// timing are `null` until the module has loaded. `metadata` holds the pairs attached with
+/// [`LazyLoader::set_metadata`](crate::LazyLoader::set_metadata).
+///
+/// ```json
+/// [{ "name": "Editor", "split": true, "state": "loaded", "size": 48213, "timing": { "fetch": 12.5, "compile": 30.1, "instantiate": 0.4 }, "metadata": { "team": "docs" } }]
+/// ```
+#[cfg(feature = "serde")]
+pub fn registry_snapshot_json() -> String {
+    let snapshot = REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .map(|loader| LoaderSnapshot {
+                name: loader.name.to_string(),
+                split: loader.load.is_some(),
+                state: state_name(loader.state.get()).to_string(),
+                size: loader.size.get(),
+                timing: loader.timing.get().map(|timing| TimingSnapshot {
+                    fetch: timing.fetch.as_secs_f64() * 1000.0,
+                    compile: timing.compile.as_secs_f64() * 1000.0,
+                    instantiate: timing.instantiate.as_secs_f64() * 1000.0,
+                }),
+                metadata: crate::metadata::get(loader),
+            })
+            .collect::<Vec<_>>()
+    });
+
+    serde_json::to_string(&snapshot).unwrap_or_default()
+}
+
+/// The name of a loader's state in snapshots and graphs
+fn state_name(state: SplitLoaderState) -> &'static str {
+    match state {
+        SplitLoaderState::Deferred(_) => "deferred",
+        SplitLoaderState::Pending => "pending",
+        SplitLoaderState::Completed(true) => "loaded",
+        SplitLoaderState::Completed(false) => "failed",
+    }
+}
+
+/// Render the split functions known on this thread, the chunks they live in and the dependencies
+/// declared between them as a Graphviz DOT graph, like to render it with `dot -Tsvg` and look at
+/// how the app is split.
+///
+/// Every chunk is a cluster labeled `chunk <n>` along with the size of its module once it loaded,
+/// holding a node for each split function in it. Function nodes are labeled with the name of the
+/// split function and the state of its loader, one of `deferred`, `pending`, `loaded` or `failed`.
+/// Loaders that were never split out are dashed nodes labeled `inlined`, and split functions the
+/// glue knows about whose loaders haven't been used yet are dotted nodes labeled `not used yet`,
+/// since it isn't known which chunk they share until then. Each dependency declared with
+/// [`declare_dependency`](crate::declare_dependency) is an edge from the dependent to its
+/// dependency. Chunks are numbered in the order their first loader was registered, so the numbers
+/// don't match the file names of the chunks.
+pub fn export_graph_dot() -> String {
+    use std::fmt::Write;
+
+    /// A quoted DOT string with a line for each part
+    fn label(lines: &[&str]) -> String {
+        let lines = lines
+            .iter()
+            .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+            .collect::<Vec<_>>();
+        format!("\"{}\"", lines.join("\\n"))
+    }
+
+    let loaders = all();
+    // Loaders that share a module share its load function
+    let mut chunks: Vec<(usize, Vec<&Rc<SplitLoader>>)> = Vec::new();
+    for loader in &loaders {
+        let Some(load) = loader.load else { continue };
+        match chunks.iter_mut().find(|(chunk, _)| *chunk == load as usize) {
+            Some((_, members)) => members.push(loader),
+            None => chunks.push((load as usize, vec![loader])),
+        }
+    }
+
+    let mut dot = String::from("digraph wasm_split {\n    node [shape=box];\n");
+    for (index, (_, members)) in chunks.iter().enumerate() {
+        let chunk = format!("chunk {index}");
+        let size = members
+            .iter()
+            .find_map(|loader| loader.size.get())
+            .map(|size| format!("{size} bytes"));
+        let chunk_label = match &size {
+            Some(size) => label(&[&chunk, size]),
+            None => label(&[&chunk]),
+        };
+        _ = writeln!(
+            dot,
+            "    subgraph cluster_{index} {{\n        label={chunk_label};"
+        );
+        for loader in members {
+            let state = state_name(loader.state.get());
+            _ = writeln!(
+                dot,
+                "        {} [label={}];",
+                label(&[loader.name]),
+                label(&[loader.name, state])
+            );
+        }
+        dot.push_str("    }\n");
+    }
+
+    for loader in loaders.iter().filter(|loader| loader.load.is_none()) {
+        _ = writeln!(
+            dot,
+            "    {} [label={}, style=dashed];",
+            label(&[loader.name]),
+            label(&[loader.name, "inlined"])
+        );
+    }
+    for name in all_loader_names() {
+        if !loaders.iter().any(|loader| loader.name == name) {
+            _ = writeln!(
+                dot,
+                "    {} [label={}, style=dotted];",
+                label(&[name]),
+                label(&[name, "not used yet"])
+            );
+        }
+    }
+
+    for (dependent, dependencies) in crate::dependencies::graph() {
+        for dependency in dependencies {
+            _ = writeln!(
+                dot,
+                "    {} -> {};",
+                label(&[&dependent]),
+                label(&[&dependency])
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// How a split function's module changed between two snapshots, as part of a [`SnapshotDiff`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkDiff {
+    /// The name of the split function
+    pub name: String,
+    /// The size of the module in bytes before and after, if it was loaded in that snapshot
+    pub size: (Option<u64>, Option<u64>),
+    /// The total load time of the module in milliseconds before and after, if it was loaded in
+    /// that snapshot
+    pub load_time: (Option<f64>, Option<f64>),
+}
+
+#[cfg(feature = "serde")]
+impl ChunkDiff {
+    /// How many bytes the module grew by, negative if it shrank. This is `None` unless the module
+    /// was loaded in both snapshots.
+    pub fn size_delta(&self) -> Option<i64> {
+        match self.size {
+            (Some(before), Some(after)) => Some(after as i64 - before as i64),
+            _ => None,
+        }
+    }
+
+    /// How many milliseconds slower the module loaded, negative if it got faster. This is `None`
+    /// unless the module was loaded in both snapshots.
+    pub fn load_time_delta(&self) -> Option<f64> {
+        match self.load_time {
+            (Some(before), Some(after)) => Some(after - before),
+            _ => None,
+        }
+    }
+}
+
+/// The differences between two registry snapshots, from [`diff_snapshots`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SnapshotDiff {
+    /// Every split function in either snapshot, in the order of the `after` snapshot followed by
+    /// the ones only in the `before` snapshot
+    pub chunks: Vec<ChunkDiff>,
+}
+
+#[cfg(feature = "serde")]
+impl SnapshotDiff {
+    /// Get the modules that grew by more than `max_growth` bytes or loaded more than
+    /// `max_slowdown` milliseconds slower, for failing a CI run on a regression:
+    ///
+    /// ```rust, ignore
+    /// let regressions = wasm_split::diff_snapshots(&baseline, &current).regressions(1024, 50.0);
+    /// assert!(regressions.is_empty(), "chunks regressed: {regressions:?}");
+    /// ```
+    ///
+    /// Modules that weren't loaded in both snapshots can't regress, since there is nothing to
+    /// compare them with.
+    pub fn regressions(&self, max_growth: u64, max_slowdown: f64) -> Vec<&ChunkDiff> {
+        self.chunks
+            .iter()
+            .filter(|chunk| {
+                chunk
+                    .size_delta()
+                    .is_some_and(|delta| delta > max_growth as i64)
+                    || chunk
+                        .load_time_delta()
+                        .is_some_and(|delta| delta > max_slowdown)
+            })
+            .collect()
+    }
+}
+
+/// Compare two registry snapshots, like the ones [`registry_snapshot_json`] saved for the last
+/// release and for the current build, to see which modules grew or got slower to load.
+///
+/// The snapshots can be deserialized from the JSON of [`registry_snapshot_json`]:
+///
+/// ```rust, ignore
+/// let before: Vec<LoaderSnapshot> = serde_json::from_str(&baseline_json)?;
+/// let after: Vec<LoaderSnapshot> = serde_json::from_str(&wasm_split::registry_snapshot_json())?;
+/// let diff = wasm_split::diff_snapshots(&before, &after);
+/// ```
+///
+/// Loaders are matched up by the name of their split function. Load times vary from run to run, so
+/// compare snapshots from several runs or leave some headroom in the thresholds.
+#[cfg(feature = "serde")]
+pub fn diff_snapshots(before: &[LoaderSnapshot], after: &[LoaderSnapshot]) -> SnapshotDiff {
+    let find = |snapshot: &[LoaderSnapshot], name: &str| {
+        snapshot
+            .iter()
+            .find(|loader| loader.name == name)
+            .map(|loader| (loader.size, loader.timing.map(|timing| timing.total())))
+            .unwrap_or_default()
+    };
+
+    let names = after.iter().chain(
+        before
+            .iter()
+            .filter(|loader| !after.iter().any(|other| other.name == loader.name)),
+    );
+    let chunks = names
+        .map(|loader| {
+            let (size_before, time_before) = find(before, &loader.name);
+            let (size_after, time_after) = find(after, &loader.name);
+            ChunkDiff {
+                name: loader.name.clone(),
+                size: (size_before, size_after),
+                load_time: (time_before, time_after),
+            }
+        })
+        .collect();
+
+    SnapshotDiff { chunks }
+}
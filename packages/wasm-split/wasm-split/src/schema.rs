@@ -0,0 +1,44 @@
+//! Checking that a split module exports what the main module expects before trusting it.
+
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+    static EXPORT_SCHEMAS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new());
+}
+
+/// Require the module of the split function `name` to export every function in `exports`.
+///
+/// The glue checks the exports of the module once it has compiled, before instantiating it, and
+/// fails the load with [`SplitLoaderError::SchemaMismatch`](crate::SplitLoaderError::SchemaMismatch)
+/// listing the missing exports if any are missing. This catches a module that drifted from the
+/// contract the main module was built against, like a module from a misconfigured deploy, which
+/// would otherwise fail later with a cryptic error. Loads failing the check aren't retried. Only
+/// the loader's own load is checked, not [`load_critical`](crate::load_critical). No split has a
+/// schema by default, and setting one again replaces it.
+///
+/// ```rust, ignore
+/// wasm_split::set_export_schema("Editor", &["editor_open", "editor_export"]);
+/// ```
+pub fn set_export_schema(name: &str, exports: &[&str]) {
+    let exports = exports.iter().map(|export| export.to_string()).collect();
+    EXPORT_SCHEMAS.with_borrow_mut(|schemas| schemas.insert(name.to_string(), exports));
+}
+
+/// Hand the export schema of the split function to the load that `start` kicks off, if one was set
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn with_export_schema(name: &str, start: impl FnOnce()) {
+    use wasm_bindgen::JsValue;
+
+    let Some(schema) = EXPORT_SCHEMAS.with_borrow(|schemas| {
+        schemas.get(name).map(|exports| {
+            exports
+                .iter()
+                .map(|export| JsValue::from_str(export))
+                .collect::<js_sys::Array>()
+        })
+    }) else {
+        return start();
+    };
+
+    crate::glue::with_load_option("exportSchema", &schema, start)
+}
@@ -0,0 +1,40 @@
+//! Precomputed results that a split function returns until its module has loaded.
+
+use crate::{loader_key, LazyLoader};
+use std::{any::Any, cell::RefCell, collections::HashMap, rc::Rc};
+
+/// An `Rc<dyn Fn() -> Ret>` returning a clone of the seed, downcast with the loader's `Ret`
+type SeedFn = Box<dyn Any>;
+
+thread_local! {
+    static SEEDS: RefCell<HashMap<*const (), SeedFn>> = RefCell::new(HashMap::new());
+}
+
+impl<Args: 'static, Ret: Clone + 'static> LazyLoader<Args, Ret> {
+    /// Return `seed` from [`LazyLoader::call`] until this loader's module has loaded, for split
+    /// functions whose first result is cheap to compute ahead of time, like at build time, so the
+    /// first paint doesn't wait for the module.
+    ///
+    /// While the loader is deferred or pending, every call returns a clone of `seed` without
+    /// starting a load, whatever the arguments. As soon as the module has loaded, calls go to the
+    /// real function, so a caller that calls again after [`LazyLoader::load`] resolves picks up the
+    /// real result. If the load fails, `seed` isn't used anymore and calls fall back to the
+    /// loader's fallback or return its error as usual. Setting a seed again replaces the old one.
+    pub fn with_seed(&'static self, seed: Ret) {
+        let clone: Rc<dyn Fn() -> Ret> = Rc::new(move || seed.clone());
+        SEEDS.with_borrow_mut(|seeds| seeds.insert(loader_key(self), Box::new(clone)));
+    }
+}
+
+/// Get a clone of the seed set on the loader with [`LazyLoader::with_seed`], if any
+pub(crate) fn get<Args: 'static, Ret: 'static>(
+    loader: &'static LazyLoader<Args, Ret>,
+) -> Option<Ret> {
+    let clone = SEEDS.with_borrow(|seeds| {
+        seeds
+            .get(&loader_key(loader))?
+            .downcast_ref::<Rc<dyn Fn() -> Ret>>()
+            .cloned()
+    })?;
+    Some(clone())
+}
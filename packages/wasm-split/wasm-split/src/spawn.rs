@@ -0,0 +1,129 @@
+//! Calling future-returning splits in the background with a handle that can cancel the call.
+
+use crate::{
+    config::{self, SpawnedFuture},
+    LazyLoader, Result, SplitLoaderError,
+};
+use futures_util::future::{AbortHandle, Abortable};
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+impl<Args: 'static, Fut: Future + 'static> LazyLoader<Args, Fut> {
+    /// Load this loader's module and call its split function in the background, returning a
+    /// handle that resolves to the output of the future the split returned.
+    ///
+    /// Unlike awaiting [`LazyLoader::call`], the call can be cancelled with [`CallHandle::abort`],
+    /// like when the user navigates away from the page that needed it. Dropping the handle doesn't
+    /// cancel the call, it keeps running and its output is dropped. It's spawned with the function
+    /// set with [`set_spawn_fn`](crate::set_spawn_fn). Outside the browser without one there is no
+    /// executor to spawn the call onto, so it only makes progress while the handle is awaited and
+    /// dropping the handle drops the call.
+    pub fn spawn_call(&'static self, args: Args) -> CallHandle<Fut::Output> {
+        let state = Rc::new(RefCell::new(CallState {
+            output: None,
+            finished: false,
+            waker: None,
+        }));
+        let (abort, registration) = AbortHandle::new_pair();
+        let call = Abortable::new(
+            {
+                let state = state.clone();
+                async move {
+                    self.load().await;
+                    let output = match self.call(args) {
+                        Ok(future) => Ok(future.await),
+                        Err(error) => Err(error),
+                    };
+                    CallState::finish(&state, output);
+                }
+            },
+            registration,
+        );
+        let call = config::spawn(Box::pin(async move {
+            _ = call.await;
+        }));
+
+        CallHandle { abort, state, call }
+    }
+}
+
+/// A call started with [`LazyLoader::spawn_call`], resolving to the output of the split's future,
+/// or to the error of the call if the module couldn't be loaded or the call was aborted.
+pub struct CallHandle<T> {
+    abort: AbortHandle,
+    state: Rc<RefCell<CallState<T>>>,
+    /// The call itself, driven by the handle when there is no executor to spawn it onto
+    call: Option<SpawnedFuture>,
+}
+
+impl<T> CallHandle<T> {
+    /// Cancel the call, resolving the handle to [`SplitLoaderError::Aborted`] unless it already
+    /// finished.
+    ///
+    /// The split's future is dropped at the point it is waiting at, so everything it owns is
+    /// dropped with it and the destructors of guards and handles it holds run as usual. Work it
+    /// handed off to something else isn't cancelled though, like tasks it spawned or a `fetch` it
+    /// started without an abort signal. If the module is still loading, the load keeps going since
+    /// other callers may share it, and only the call is skipped.
+    pub fn abort(&self) {
+        self.abort.abort();
+        CallState::finish(&self.state, Err(SplitLoaderError::Aborted));
+    }
+
+    /// Check whether the call finished, successfully, with an error or by being aborted
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().finished
+    }
+}
+
+impl<T> Future for CallHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let this = self.get_mut();
+        if let Some(call) = this.call.as_mut() {
+            if call.as_mut().poll(cx).is_ready() {
+                this.call = None;
+            }
+        }
+
+        let mut state = this.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The output of a spawned call, shared between the call and its handle
+struct CallState<T> {
+    output: Option<Result<T>>,
+    finished: bool,
+    waker: Option<Waker>,
+}
+
+impl<T> CallState<T> {
+    /// Store the output of the call unless it already finished, and wake the task awaiting it
+    fn finish(state: &RefCell<Self>, output: Result<T>) {
+        let waker = {
+            let mut state = state.borrow_mut();
+            if state.finished {
+                return;
+            }
+            state.finished = true;
+            state.output = Some(output);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Utilities for testing how an app handles split modules that fail to load, and for mocking them
+//! as loaded in environments where they aren't served.
+
+use crate::{registry, LoadPriority, SplitLoader, SplitLoaderError, SplitLoaderState};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::Duration,
+};
+
+/// A timer scheduled while a clock is set, with the time on the clock it's due at
+type Timer = (f64, Box<dyn FnOnce()>);
+
+thread_local! {
+    static INJECTOR: RefCell<Option<InstalledInjector>> = const { RefCell::new(None) };
+    static CLOCK: RefCell<Option<Rc<dyn Fn() -> f64>>> = const { RefCell::new(None) };
+    static TIMERS: RefCell<Vec<Timer>> = const { RefCell::new(Vec::new()) };
+    /// The loaders marked as loaded by `mock_all_loaded` whose modules never actually loaded
+    static MOCKED: RefCell<HashSet<*const SplitLoader>> = RefCell::new(HashSet::new());
+    static DRY_RUN: RefCell<DryRun> = const {
+        RefCell::new(DryRun {
+            enabled: false,
+            loaded: true,
+            log: Vec::new(),
+        })
+    };
+}
+
+struct DryRun {
+    enabled: bool,
+    loaded: bool,
+    log: Vec<DryRunEntry>,
+}
+
+struct InstalledInjector {
+    failures: Vec<Failure>,
+    attempts: HashMap<String, usize>,
+}
+
+struct Failure {
+    name: String,
+    attempt: usize,
+    error: SplitLoaderError,
+}
+
+/// Deterministically fail loads of split modules without touching the network.
+///
+/// Each failure targets the nth load attempt of a loader, counted per loader name from `1` since
+/// the injector was installed. The loader fails before its module is fetched, exactly as if the
+/// download had failed, and every other attempt goes through as usual.
+///
+/// ```rust, ignore
+/// use wasm_split::testing::FailureInjector;
+///
+/// FailureInjector::new()
+///     .fail_nth("Editor", 1)
+///     .fail_nth_with("Chart", 2, SplitLoaderError::VersionMismatch)
+///     .install();
+///
+/// // ... exercise the app
+///
+/// FailureInjector::reset();
+/// ```
+#[derive(Default)]
+pub struct FailureInjector {
+    failures: Vec<Failure>,
+}
+
+impl FailureInjector {
+    /// Create an injector that doesn't fail any loads yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the nth load attempt of the loader with the given name with [`SplitLoaderError::FailedToLoad`]
+    pub fn fail_nth(self, name: impl Into<String>, attempt: usize) -> Self {
+        self.fail_nth_with(name, attempt, SplitLoaderError::FailedToLoad)
+    }
+
+    /// Fail the nth load attempt of the loader with the given name with a specific error
+    pub fn fail_nth_with(
+        mut self,
+        name: impl Into<String>,
+        attempt: usize,
+        error: SplitLoaderError,
+    ) -> Self {
+        self.failures.push(Failure {
+            name: name.into(),
+            attempt,
+            error,
+        });
+        self
+    }
+
+    /// Start injecting failures on this thread, replacing any injector that was installed before
+    /// and restarting the attempt counts.
+    pub fn install(self) {
+        INJECTOR.set(Some(InstalledInjector {
+            failures: self.failures,
+            attempts: HashMap::new(),
+        }));
+    }
+
+    /// Remove the installed injector so loads go through as usual again.
+    ///
+    /// Call this between tests so failures and attempt counts don't leak into the next test.
+    pub fn reset() {
+        INJECTOR.set(None);
+    }
+}
+
+/// Count a load attempt of the loader, returning the error to fail it with if one was injected
+pub(crate) fn injected_failure(name: &str) -> Option<SplitLoaderError> {
+    INJECTOR.with_borrow_mut(|injector| {
+        let injector = injector.as_mut()?;
+        let attempt = injector.attempts.entry(name.to_string()).or_default();
+        *attempt += 1;
+        let attempt = *attempt;
+
+        injector
+            .failures
+            .iter()
+            .find(|failure| failure.name == name && failure.attempt == attempt)
+            .map(|failure| failure.error.clone())
+    })
+}
+
+/// Mark every loader registered on this thread as loaded without fetching its module, for
+/// component galleries and docs where the split modules aren't served.
+///
+/// Lazy components render their loaded state instead of suspending, and anything awaiting the
+/// loaders resolves with `true`. Loaders whose modules already loaded, and loaders that were never
+/// split out, are left alone. Since loaders register the first time they're used, call this after
+/// the loaders you want to mock have been touched, e.g. once the gallery has rendered.
+///
+/// The module of a mocked loader was never instantiated, so its split function doesn't exist yet
+/// and calling the import would trap. Instead, [`LazyLoader::call`](crate::LazyLoader::call) on a
+/// mocked loader never touches the import: it calls the fallback set with
+/// [`LoaderConfig::fallback`](crate::LoaderConfig::fallback) if there is one, and otherwise returns
+/// [`SplitLoaderError::Mocked`].
+pub fn mock_all_loaded() {
+    for loader in registry::all() {
+        if loader.load.is_none() || loader.is_loaded() {
+            continue;
+        }
+
+        mock(&loader);
+        loader.wake();
+    }
+}
+
+/// Mark the loader as loaded without its module
+fn mock(loader: &Rc<SplitLoader>) {
+    MOCKED.with_borrow_mut(|mocked| mocked.insert(Rc::as_ptr(loader)));
+    loader.error.replace(None);
+    // A load that's still in flight finds the loader completed and leaves it alone
+    loader.set_state(SplitLoaderState::Completed(true));
+}
+
+/// Check whether a loader was marked as loaded by `mock_all_loaded`
+pub(crate) fn is_mocked(loader: &SplitLoader) -> bool {
+    MOCKED.with_borrow(|mocked| mocked.contains(&(loader as *const SplitLoader)))
+}
+
+/// A load recorded by [`dry_run`], listed by [`dry_run_log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DryRunEntry {
+    /// The name of the split function whose module would have been loaded
+    pub name: &'static str,
+
+    /// The priority the module would have been fetched with
+    pub priority: LoadPriority,
+}
+
+/// Record the loads of split modules instead of running them, for unit testing the logic that
+/// decides what to load and when, like preload scheduling and grouping, without a network.
+///
+/// While enabled, every load that would fetch a module is listed by [`dry_run_log`] in the order
+/// the loads would have started, and then completes right away with the result set with
+/// [`set_dry_run_result`], which defaults to loaded. Loads that would have loaded are marked as
+/// loaded like with [`mock_all_loaded`], so calling their split functions goes to the fallback or
+/// returns [`SplitLoaderError::Mocked`], and loads that would have failed fail with
+/// [`SplitLoaderError::FailedToLoad`] and are retried and logged again as their policies say.
+///
+/// The log follows the runtime's scheduling: loads queued while [`pause_loads`](crate::pause_loads)
+/// is in effect are recorded when [`resume_loads`](crate::resume_loads) starts them, in the order
+/// of their priorities, and the dependencies declared with
+/// [`declare_dependency`](crate::declare_dependency) are recorded before the splits that depend on
+/// them. [`load_all_ordered`](crate::load_all_ordered) and
+/// [`load_all_as_completed`](crate::load_all_as_completed) start their loaders in the order they're
+/// listed, so their loads are recorded in that order. Loaders that are already loaded or loading
+/// don't start a load, so they aren't recorded. Failures injected with [`FailureInjector`] fail
+/// their loads before they're recorded. Modules loaded straight from the glue by name, like with
+/// [`load_critical`](crate::load_critical), don't go through a loader and still load for real.
+pub fn dry_run(enabled: bool) {
+    DRY_RUN.with_borrow_mut(|dry_run| dry_run.enabled = enabled);
+}
+
+/// Set whether the loads recorded by [`dry_run`] load or fail.
+pub fn set_dry_run_result(loaded: bool) {
+    DRY_RUN.with_borrow_mut(|dry_run| dry_run.loaded = loaded);
+}
+
+/// Get every load recorded by [`dry_run`] on this thread, in the order they would have started.
+pub fn dry_run_log() -> Vec<DryRunEntry> {
+    DRY_RUN.with_borrow(|dry_run| dry_run.log.clone())
+}
+
+/// Record the load of the loader if a dry run is enabled, returning whether it would have loaded.
+/// A load that would have loaded is completed already.
+pub(crate) fn record_dry_run(loader: &Rc<SplitLoader>) -> Option<bool> {
+    let loaded = DRY_RUN.with_borrow_mut(|dry_run| {
+        if !dry_run.enabled {
+            return None;
+        }
+        dry_run.log.push(DryRunEntry {
+            name: loader.name,
+            priority: loader.policy.borrow().priority,
+        });
+        Some(dry_run.loaded)
+    })?;
+
+    if loaded {
+        mock(loader);
+        loader.wake();
+    }
+    Some(loaded)
+}
+
+/// Read the time from `clock` instead of `performance.now()`, so tests can advance a virtual clock
+/// and assert on the durations the runtime measures without waiting for real time to pass.
+///
+/// The clock returns the time in milliseconds. It's used for every timestamp the runtime takes:
+/// the phases of [`LoadTiming`](crate::LoadTiming) measured by the glue, how long lazy components
+/// were suspended, the time to the first call, the deadlines of
+/// [`LazyLoader::load_with_deadline`](crate::LazyLoader::load_with_deadline), the throttling of
+/// progress callbacks and the windows of the waterfall detection.
+///
+/// Timers run on the clock as well: the timeouts of loads and of
+/// [`LazyLoader::call_with_timeout`](crate::LazyLoader::call_with_timeout), the deadlines, the
+/// fallback delays of lazy components and the debounce of preloads only fire once
+/// [`run_due_timers`] is called after the clock passed their time, and never on their own. Timers
+/// scheduled before the clock was set keep running on real time. The clock and its timers are
+/// dropped again by [`reset_all`].
+///
+/// ```rust, ignore
+/// let time = Rc::new(Cell::new(0.0));
+/// wasm_split::testing::set_clock({
+///     let time = time.clone();
+///     move || time.get()
+/// });
+/// time.set(time.get() + 250.0);
+/// wasm_split::testing::run_due_timers();
+/// ```
+pub fn set_clock(clock: impl Fn() -> f64 + 'static) {
+    let clock: Rc<dyn Fn() -> f64> = Rc::new(clock);
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::closure::Closure;
+
+        let read = {
+            let clock = clock.clone();
+            Closure::<dyn Fn() -> f64>::new(move || clock()).into_js_value()
+        };
+        crate::glue::set_option_value("clock", &read);
+
+        // The timeouts of fetches are set up by the glue, so it schedules them here too
+        let set_timer =
+            Closure::<dyn Fn(f64, js_sys::Function)>::new(|ms: f64, callback: js_sys::Function| {
+                crate::glue::set_timeout(Duration::from_secs_f64(ms.max(0.0) / 1000.0), move || {
+                    _ = callback.call0(&wasm_bindgen::JsValue::UNDEFINED);
+                })
+            })
+            .into_js_value();
+        crate::glue::set_option_value("setTimer", &set_timer);
+    }
+    CLOCK.set(Some(clock));
+}
+
+/// Fire every timer that is due on the clock set with [`set_clock`], earliest first, returning how
+/// many fired.
+///
+/// Timers that are due at the same time fire in the order they were scheduled, and timers that
+/// the fired ones schedule fire as well if they're due by then. Advance the clock and call this
+/// instead of waiting for real time to pass. Without a clock nothing is scheduled on it, so
+/// nothing fires.
+pub fn run_due_timers() -> usize {
+    let Some(now) = now() else {
+        return 0;
+    };
+
+    let mut fired = 0;
+    loop {
+        let timer = TIMERS.with_borrow_mut(|timers| {
+            let (next, _) = timers
+                .iter()
+                .enumerate()
+                .filter(|(_, (due, _))| *due <= now)
+                .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b))?;
+            Some(timers.remove(next))
+        });
+        let Some((_, run)) = timer else {
+            return fired;
+        };
+        run();
+        fired += 1;
+    }
+}
+
+/// Schedule `f` on the timers of the clock set with [`set_clock`], or hand it back if there's no
+/// clock so it runs on real time
+pub(crate) fn schedule<F: FnOnce() + 'static>(timeout: Duration, f: F) -> Option<F> {
+    let Some(now) = now() else {
+        return Some(f);
+    };
+    let due = now + timeout.as_secs_f64() * 1000.0;
+    TIMERS.with_borrow_mut(|timers| timers.push((due, Box::new(f))));
+    None
+}
+
+/// Read the clock set with [`set_clock`], if any
+pub(crate) fn now() -> Option<f64> {
+    let clock = CLOCK.with_borrow(|clock| clock.clone())?;
+    Some(clock())
+}
+
+/// Reset every loader registered on this thread so the next test starts from a clean slate.
+///
+/// Loaders go back to not being loaded, as if they were never used, and forget the timing, size and
+/// errors of their loads along with the [`LazyLoader::on_loaded`](crate::LazyLoader::on_loaded)
+/// callbacks that haven't run yet. The installed [`FailureInjector`] and the loaders mocked with
+/// [`mock_all_loaded`] are dropped as well, and so are the clock set with [`set_clock`] with its
+/// timers and the waterfalls listed by [`waterfall_warnings`](crate::waterfall_warnings). A
+/// [`dry_run`] is turned off, and its log and result are reset. Call this in the setup or teardown of each test.
+///
+/// Loaders whose load is still in flight are left to finish it. The browser keeps the modules
+/// that already loaded, so loading a reset loader again completes right away without fetching
+/// anything, and initializers set with
+/// [`LazyLoader::with_post_load`](crate::LazyLoader::with_post_load) don't run again.
+pub fn reset_all() {
+    FailureInjector::reset();
+    MOCKED.take();
+    crate::waterfall::reset();
+    CLOCK.take();
+    TIMERS.take();
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::glue::set_option_value("clock", &wasm_bindgen::JsValue::UNDEFINED);
+        crate::glue::set_option_value("setTimer", &wasm_bindgen::JsValue::UNDEFINED);
+    }
+    DRY_RUN.set(DryRun {
+        enabled: false,
+        loaded: true,
+        log: Vec::new(),
+    });
+
+    for loader in registry::all() {
+        let Some(load) = loader.load else {
+            continue;
+        };
+        if let SplitLoaderState::Pending = loader.state.get() {
+            continue;
+        }
+
+        loader.set_state(SplitLoaderState::Deferred(load));
+        loader.timing.set(None);
+        loader.memory_delta.set(None);
+        loader.size.set(None);
+        loader.cache_hit.set(None);
+        loader.exports.take();
+        #[cfg(target_arch = "wasm32")]
+        loader.module.take();
+        loader.error.take();
+        loader.attempts.set(0);
+        loader.failures.set(0);
+        loader.loaded_at.set(None);
+        loader.time_to_first_call.set(None);
+        loader.calls.set(0);
+        loader.on_loaded.take();
+    }
+}
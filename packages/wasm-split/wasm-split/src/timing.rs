@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+/// How long each phase of loading a split module took.
+///
+/// The fetch phase covers the time until the response headers arrived. The body is streamed into
+/// the compiler, so the compile phase includes downloading the rest of the module.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadTiming {
+    /// The time spent waiting for the response to the module's request
+    pub fetch: Duration,
+
+    /// The time spent downloading and compiling the module
+    pub compile: Duration,
+
+    /// The time spent instantiating the compiled module
+    pub instantiate: Duration,
+}
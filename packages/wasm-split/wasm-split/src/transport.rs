@@ -0,0 +1,27 @@
+use crate::{glue, Result};
+use std::{future::Future, pin::Pin};
+
+/// A way of downloading the bytes of split modules.
+///
+/// By default the glue fetches modules over HTTP and streams them straight into the compiler. A
+/// custom transport set with [`set_transport`](crate::set_transport) can fetch them some other way,
+/// like over a WebRTC data channel. The glue then compiles and instantiates the returned bytes.
+pub trait ChunkTransport {
+    /// Fetch the bytes of a module. The name is the url of the module as written by the bundler,
+    /// resolved against the base url set with [`set_chunk_base_url`](crate::set_chunk_base_url).
+    fn fetch(&self, name: &str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>>>>;
+}
+
+/// The default transport that fetches modules over HTTP with the browser's `fetch`.
+///
+/// Unlike the glue's built-in fetching, this buffers the whole module before compiling it. It's
+/// mostly useful as a building block for transports that wrap HTTP, like ones that fall back to it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpTransport;
+
+impl ChunkTransport for HttpTransport {
+    fn fetch(&self, name: &str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>>>> {
+        let url = name.to_string();
+        Box::pin(async move { glue::fetch_bytes(&url).await })
+    }
+}
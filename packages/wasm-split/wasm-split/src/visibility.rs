@@ -0,0 +1,44 @@
+//! Retrying loads that failed while the tab was in the background once it's visible again.
+
+use crate::{glue, registry, SplitLoaderError};
+use std::cell::Cell;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Rearm every failed loader whenever the page becomes visible again, returning whether the
+/// browser supports listening for it.
+///
+/// Browsers throttle background tabs, which makes loads that run while the tab is hidden more
+/// likely to time out or fail. Every time the page becomes visible, each loader whose load failed
+/// goes back to not being loaded like with [`LazyLoader::rearm`](crate::LazyLoader::rearm), so the
+/// next load retries it. Loads that failed with an error that isn't
+/// [retryable](SplitLoaderError::is_retryable) aren't rearmed, like loaders marked as failed with
+/// [`LazyLoader::mark_failed`](crate::LazyLoader::mark_failed), since trying them again wouldn't
+/// help.
+///
+/// Rearming doesn't start a load by itself, so this can't retry in a loop: a rearmed loader is only
+/// loaded again once something asks for it, and then gets the retries of its
+/// [`LoaderConfig`](crate::LoaderConfig) like any other load. Each time the tab comes back into
+/// view, failed loaders get one more round of retries. Calling this again does nothing.
+pub fn enable_retry_on_visible() -> bool {
+    if ENABLED.replace(true) {
+        return true;
+    }
+
+    let installed = glue::on_visible(|| {
+        for loader in registry::all() {
+            let retryable = loader
+                .error
+                .borrow()
+                .as_ref()
+                .map_or(true, SplitLoaderError::is_retryable);
+            if retryable {
+                loader.rearm();
+            }
+        }
+    });
+    ENABLED.set(installed);
+    installed
+}
@@ -0,0 +1,74 @@
+//! Spotting chunks whose first calls immediately load another chunk, which makes the user wait for
+//! one download after the other instead of both at once.
+
+use crate::{glue, SplitLoader};
+use std::cell::{Cell, RefCell};
+
+/// How soon after a module loaded a call into it counts as part of the load, and how soon after
+/// that call a load of another module counts as triggered by it, in milliseconds
+const WINDOW_MS: f64 = 100.0;
+
+thread_local! {
+    /// The split function that was last called shortly after its module loaded, and when
+    static LAST_CALL: Cell<Option<(&'static str, f64)>> = const { Cell::new(None) };
+    static WARNINGS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get every chunk→chunk waterfall spotted so far on this thread, as the name of the split function
+/// that was called and the name of the one whose load it triggered, in the order they were spotted.
+///
+/// A waterfall is spotted when a split function is called within 100ms of its module loading, and
+/// another split starts loading within 100ms of that call. That's the pattern of a lazy component
+/// whose first render uses another lazy split: the second download only starts once the first has
+/// finished. Preloading the second split alongside the first, like with
+/// [`declare_dependency`](crate::declare_dependency), flattens it. Each sequence is listed and logged
+/// as a warning once. This goes by timing alone, so a load that an unrelated task happens to start
+/// right after such a call is reported too.
+pub fn waterfall_warnings() -> Vec<(String, String)> {
+    WARNINGS.with_borrow(|warnings| warnings.clone())
+}
+
+/// Remember a call into the loader if its module loaded just before
+pub(crate) fn record_call(loader: &SplitLoader) {
+    let Some(loaded_at) = loader.loaded_at.get() else {
+        return;
+    };
+    let now = glue::now();
+    if now - loaded_at <= WINDOW_MS {
+        LAST_CALL.set(Some((loader.name, now)));
+    }
+}
+
+/// Record a waterfall if the loader with this name started loading right after a call into a
+/// module that just loaded
+pub(crate) fn record_start(name: &'static str) {
+    let Some((caller, called_at)) = LAST_CALL.get() else {
+        return;
+    };
+    if caller == name || glue::now() - called_at > WINDOW_MS {
+        return;
+    }
+
+    let new = WARNINGS.with_borrow_mut(|warnings| {
+        if warnings
+            .iter()
+            .any(|(from, to)| from == caller && to == name)
+        {
+            return false;
+        }
+        warnings.push((caller.to_string(), name.to_string()));
+        true
+    });
+    if new {
+        crate::warn_log(format_args!(
+            "chunk waterfall: calling `{caller}` right after it loaded started loading `{name}`, consider preloading `{name}` along with `{caller}`"
+        ));
+    }
+}
+
+/// Forget every waterfall spotted so far
+#[cfg(feature = "testing")]
+pub(crate) fn reset() {
+    LAST_CALL.set(None);
+    WARNINGS.take();
+}